@@ -1,22 +1,77 @@
+/// Forwarded from `.env` (or the environment) to the firmware, see `.env.example`
+const ENV_KEYS: &[&str] = &[
+    "WIFI_SSID",
+    "WIFI_PASSWORD",
+    "PHOTO_SOURCE",
+    "SYN_BASE",
+    "SYN_USER",
+    "SYN_PASS",
+    "SYN_API",
+    "SYN_ALBUM",
+    "SYN_FOLDER",
+    "SYN_FOLDER_RECURSIVE",
+    "SYN_PATH_PREFIX",
+    "IMMICH_BASE",
+    "IMMICH_API_KEY",
+    "IMMICH_ALBUM",
+    "IMAGE_URL",
+    "IMAGE_URL_TOKEN",
+    "WEBDAV_URL",
+    "WEBDAV_USER",
+    "WEBDAV_PASS",
+    "S3_ENDPOINT",
+    "S3_BUCKET",
+    "S3_PREFIX",
+    "S3_REGION",
+    "S3_ACCESS_KEY",
+    "S3_SECRET_KEY",
+    "NTP_SERVER",
+    "SD_FOLDER",
+    "SD_CACHE",
+    "SD_CACHE_MAX",
+    "MQTT_HOST",
+    "MQTT_PORT",
+    "MQTT_USER",
+    "MQTT_PASS",
+    "MQTT_TOPIC",
+    "LOW_BATTERY_PERCENT",
+    "NOTIFY_SERVICE",
+    "NOTIFY_URL",
+    "NOTIFY_TOKEN",
+    "DEVICE_NAME",
+    "RESIZE_FILTER",
+    "SCALE_MODE",
+    "CROP_ANCHOR",
+    "MAT_STYLE",
+    "MAT_COLOR",
+    "MAT_BORDER",
+    "PALETTE_SPACE",
+    "PANEL_PALETTE",
+    "GAMUT_MAP",
+    "GAMUT_KNEE",
+    "DITHER",
+    "DITHER_SERPENTINE",
+    "DITHER_LINEAR",
+    "DITHER_ERROR_LIMIT",
+    "DITHER_ERROR_KEEP",
+    "TONE_AUTO_LEVELS",
+    "TONE_CONTRAST",
+    "TONE_GAMMA",
+    "TONE_SATURATION",
+    "TONE_VIBRANCE",
+    "TONE_WHITE_BALANCE",
+    "TONE_SHARPEN",
+    "HTTP_HEADERS",
+    "HTTP_BASIC_AUTH",
+    "ALBUM_CACHE_HOURS",
+];
+
 fn main() {
     dotenvy::from_filename(".env").ok();
-    if let Ok(e) = std::env::var("WIFI_SSID") {
-        println!("cargo:rustc-env=WIFI_SSID={e}");
-    }
-    if let Ok(e) = std::env::var("WIFI_PASSWORD") {
-        println!("cargo:rustc-env=WIFI_PASSWORD={e}");
-    }
-    if let Ok(e) = std::env::var("SYN_BASE") {
-        println!("cargo:rustc-env=SYN_BASE={e}");
-    }
-    if let Ok(e) = std::env::var("SYN_USER") {
-        println!("cargo:rustc-env=SYN_USER={e}");
-    }
-    if let Ok(e) = std::env::var("SYN_PASS") {
-        println!("cargo:rustc-env=SYN_PASS={e}");
-    }
-        if let Ok(e) = std::env::var("SYN_ALBUM") {
-        println!("cargo:rustc-env=SYN_ALBUM={e}");
+    for key in ENV_KEYS {
+        if let Ok(e) = std::env::var(key) {
+            println!("cargo:rustc-env={key}={e}");
+        }
     }
 
     linker_be_nice();
@@ -0,0 +1,104 @@
+use alloc::string::ToString;
+use alloc::vec::Vec;
+
+use crate::photo::{MAX_PHOTOS, PhotoEntry};
+
+// Immich listings, https://immich.app/docs/api
+
+/// Photos in an album response or a random search, which is just the list.
+/// `None` when it's neither
+pub fn parse_assets(data: &[u8]) -> Option<Vec<PhotoEntry>> {
+    let stuff: serde_json::Value = serde_json::from_slice(data).ok()?;
+
+    let assets = stuff["assets"].as_array().or(stuff.as_array())?;
+
+    Some(
+        assets
+            .iter()
+            .filter(|asset| asset["type"].as_str() == Some("IMAGE"))
+            .filter_map(|asset| {
+                Some(PhotoEntry {
+                    id: asset["id"].as_str()?.to_string(),
+                    key: None,
+                    name: asset["originalFileName"].as_str().map(ToString::to_string),
+                    taken_at: None,
+                    focus: None,
+                })
+            })
+            .take(MAX_PHOTOS)
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Trimmed from Immich v1.132, `GET /api/albums/{id}`
+    const ALBUM: &str = r#"{
+        "albumName": "Frame",
+        "id": "0d3c4a9e-5b7f-4a51-9c55-2f0f3e1e9a11",
+        "assetCount": 3,
+        "assets": [
+            {
+                "id": "b6f1a0c2-7d4e-4c1b-8f7a-3e2d1c0b9a87",
+                "type": "IMAGE",
+                "originalFileName": "IMG_0001.jpg",
+                "originalMimeType": "image/jpeg",
+                "fileCreatedAt": "2024-06-01T10:00:00.000Z",
+                "isFavorite": false
+            },
+            {
+                "id": "c7e2b1d3-8e5f-4d2c-9a8b-4f3e2d1c0b98",
+                "type": "VIDEO",
+                "originalFileName": "MOV_0002.mp4",
+                "originalMimeType": "video/mp4"
+            },
+            {
+                "id": "d8f3c2e4-9f60-4e3d-ab9c-5a4f3e2d1c09",
+                "type": "IMAGE",
+                "originalMimeType": "image/heic"
+            }
+        ],
+        "shared": false
+    }"#;
+
+    // `POST /api/search/random` is a bare list
+    const RANDOM: &str = r#"[
+        {"id": "e9a4d3f5-a071-4f4e-bcad-6b5a4f3e2d1a", "type": "IMAGE", "originalFileName": "beach.jpg"},
+        {"id": "fab5e4a6-b182-405f-8dbe-7c6b5a4f3e2b", "type": "IMAGE", "originalFileName": "dog.jpg"}
+    ]"#;
+
+    #[test]
+    fn album_response() {
+        let entries = parse_assets(ALBUM.as_bytes()).unwrap();
+        assert_eq!(entries.len(), 2);
+
+        assert_eq!(entries[0].id, "b6f1a0c2-7d4e-4c1b-8f7a-3e2d1c0b9a87");
+        assert_eq!(entries[0].name.as_deref(), Some("IMG_0001.jpg"));
+        assert!(entries[0].key.is_none());
+
+        // No file name is fine, videos are skipped
+        assert_eq!(entries[1].id, "d8f3c2e4-9f60-4e3d-ab9c-5a4f3e2d1c09");
+        assert_eq!(entries[1].name, None);
+    }
+
+    #[test]
+    fn random_response() {
+        let entries = parse_assets(RANDOM.as_bytes()).unwrap();
+        let names: Vec<_> = entries.iter().filter_map(|e| e.name.as_deref()).collect();
+        assert_eq!(names, ["beach.jpg", "dog.jpg"]);
+    }
+
+    #[test]
+    fn empty_album() {
+        let entries = parse_assets(br#"{"id": "x", "assets": []}"#).unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn not_a_listing() {
+        assert!(parse_assets(b"<html>Bad gateway</html>").is_none());
+        assert!(parse_assets(br#"{"message": "Invalid API key", "statusCode": 401}"#).is_none());
+    }
+}
@@ -0,0 +1,11 @@
+#![cfg_attr(not(test), no_std)]
+
+extern crate alloc;
+
+pub mod immich;
+pub mod mqtt;
+pub mod photo;
+pub mod s3;
+pub mod sd_card;
+pub mod webdav;
+pub mod xml;
@@ -0,0 +1,174 @@
+use alloc::string::String;
+use alloc::vec::Vec;
+
+// MQTT 3.1.1 packets, just the ones the frame sends and PUBLISH coming back
+
+const KEEP_ALIVE_SECS: u16 = 60;
+
+pub struct Message {
+    pub topic: String,
+    pub payload: Vec<u8>,
+}
+
+/// Fixed header, remaining length and `body`
+pub fn packet(header: u8, body: &[u8]) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    packet.push(header);
+    let mut len = body.len();
+    loop {
+        let mut byte = (len % 128) as u8;
+        len /= 128;
+        if len > 0 {
+            byte |= 0x80;
+        }
+        packet.push(byte);
+        if len == 0 {
+            break;
+        }
+    }
+    packet.extend_from_slice(body);
+    packet
+}
+
+pub fn connect_packet(client_id: &str, credentials: Option<(&str, &str)>) -> Vec<u8> {
+    // Clean session, nothing to resume between wakes
+    let mut flags = 0x02;
+    let mut body = Vec::new();
+    put_str(&mut body, "MQTT");
+    body.push(4);
+    if credentials.is_some() {
+        flags |= 0x80 | 0x40;
+    }
+    body.push(flags);
+    body.extend_from_slice(&KEEP_ALIVE_SECS.to_be_bytes());
+    put_str(&mut body, client_id);
+    if let Some((user, pass)) = credentials {
+        put_str(&mut body, user);
+        put_str(&mut body, pass);
+    }
+    packet(0x10, &body)
+}
+
+pub fn publish_packet(topic: &str, payload: &[u8], retain: bool) -> Vec<u8> {
+    let mut body = Vec::with_capacity(topic.len() + payload.len() + 2);
+    put_str(&mut body, topic);
+    body.extend_from_slice(payload);
+    packet(0x30 | retain as u8, &body)
+}
+
+pub fn subscribe_packet(topics: &[&str]) -> Vec<u8> {
+    let mut body = Vec::new();
+    // Packet id, we never have more than one in flight
+    body.extend_from_slice(&1u16.to_be_bytes());
+    for topic in topics {
+        put_str(&mut body, topic);
+        body.push(0);
+    }
+    packet(0x82, &body)
+}
+
+/// PUBLISH after the fixed header, `kind` is the fixed header's first byte. `None` when it
+/// doesn't hold together
+pub fn parse_publish(kind: u8, body: &[u8]) -> Option<Message> {
+    let topic_len = u16::from_be_bytes([*body.first()?, *body.get(1)?]) as usize;
+    let topic = String::from_utf8(body.get(2..2 + topic_len)?.to_vec()).ok()?;
+
+    // QoS 1 and 2 carry a packet id, shouldn't happen since we subscribe with 0
+    let mut start = 2 + topic_len;
+    if (kind >> 1) & 0x03 != 0 {
+        start += 2;
+    }
+
+    Some(Message {
+        topic,
+        payload: body.get(start..).unwrap_or_default().to_vec(),
+    })
+}
+
+fn put_str(buf: &mut Vec<u8>, value: &str) {
+    buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+    buf.extend_from_slice(value.as_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn connect_with_credentials() {
+        let packet = connect_packet("frame", Some(("user", "pw")));
+        assert_eq!(
+            packet,
+            [
+                0x10, 27, // CONNECT, remaining length
+                0, 4, b'M', b'Q', b'T', b'T', 4, // protocol name and level
+                0xC2, 0, 60, // username, password, clean session, keep alive
+                0, 5, b'f', b'r', b'a', b'm', b'e', //
+                0, 4, b'u', b's', b'e', b'r', //
+                0, 2, b'p', b'w',
+            ]
+        );
+    }
+
+    #[test]
+    fn connect_without_credentials() {
+        let packet = connect_packet("f", None);
+        assert_eq!(packet[..2], [0x10, 13]);
+        assert_eq!(packet[9], 0x02);
+        assert_eq!(packet[12..], [0, 1, b'f']);
+    }
+
+    #[test]
+    fn remaining_length() {
+        assert_eq!(packet(0xE0, &[]), [0xE0, 0]);
+        assert_eq!(packet(0x30, &[0; 127])[..2], [0x30, 0x7F]);
+        assert_eq!(packet(0x30, &[0; 128])[..3], [0x30, 0x80, 0x01]);
+        assert_eq!(packet(0x30, &[0; 16_383])[..3], [0x30, 0xFF, 0x7F]);
+        assert_eq!(packet(0x30, &[0; 16_384])[..4], [0x30, 0x80, 0x80, 0x01]);
+    }
+
+    #[test]
+    fn publish() {
+        assert_eq!(
+            publish_packet("a/b", b"on", true),
+            [0x31, 7, 0, 3, b'a', b'/', b'b', b'o', b'n']
+        );
+        assert_eq!(publish_packet("a", b"", false), [0x30, 3, 0, 1, b'a']);
+    }
+
+    #[test]
+    fn subscribe() {
+        assert_eq!(
+            subscribe_packet(&["a", "b/c"]),
+            [0x82, 12, 0, 1, 0, 1, b'a', 0, 0, 3, b'b', b'/', b'c', 0]
+        );
+    }
+
+    #[test]
+    fn publish_qos_0() {
+        let message = parse_publish(0x30, &[0, 3, b'a', b'/', b'b', b'o', b'n']).unwrap();
+        assert_eq!(message.topic, "a/b");
+        assert_eq!(message.payload, b"on");
+
+        // Retained, same layout
+        let message = parse_publish(0x31, &[0, 1, b'a']).unwrap();
+        assert_eq!(message.topic, "a");
+        assert!(message.payload.is_empty());
+    }
+
+    #[test]
+    fn publish_qos_1() {
+        // Packet id 0x0102 between topic and payload
+        let message = parse_publish(0x32, &[0, 1, b'a', 1, 2, b'x']).unwrap();
+        assert_eq!(message.topic, "a");
+        assert_eq!(message.payload, b"x");
+    }
+
+    #[test]
+    fn bad_publish() {
+        assert!(parse_publish(0x30, &[]).is_none());
+        // Topic longer than the packet
+        assert!(parse_publish(0x30, &[0, 5, b'a']).is_none());
+        assert!(parse_publish(0x30, &[0, 1, 0xFF]).is_none());
+    }
+}
@@ -0,0 +1,52 @@
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+
+/// Most entries a backend lists, keeps the listing small enough for the flash cache
+pub const MAX_PHOTOS: usize = 500;
+
+/// One photo a source can fetch, kept small because whole listings are cached in flash
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct PhotoEntry {
+    pub id: String,
+    /// Anything else the source needs to fetch it, e.g. the Synology thumbnail cache key
+    #[serde(default)]
+    pub key: Option<String>,
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Unix seconds
+    #[serde(default)]
+    pub taken_at: Option<i64>,
+    /// Faces, in fractions of the photo, when the source knows where they are
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub focus: Option<Focus>,
+}
+
+/// Part of the photo that matters, in fractions of the photo's size
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Focus {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl Focus {
+    /// Smallest box around both
+    pub fn union(self, other: Self) -> Self {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Self {
+            x,
+            y,
+            width: (self.x + self.width).max(other.x + other.width) - x,
+            height: (self.y + self.height).max(other.y + other.height) - y,
+        }
+    }
+}
+
+/// By the file's extension, for backends that only have a name to go on
+pub fn is_jpeg(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    name.ends_with(".jpg") || name.ends_with(".jpeg")
+}
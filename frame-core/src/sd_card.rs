@@ -0,0 +1,343 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use embedded_sdmmc::{
+    BlockDevice, Mode, RawDirectory, RawFile, RawVolume, ShortFileName, TimeSource, Timestamp,
+    VolumeIdx, VolumeManager,
+};
+
+// FAT formatted microSD card, or anything else that's a `BlockDevice`. embedded-sdmmc
+// only does 8.3 names, longer names show up as their `PHOTO~1.JPG` short name
+
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    NotFound,
+    /// File is bigger than the caller wants to hold in memory
+    TooLarge,
+    /// Card or filesystem trouble, what went wrong is for the log
+    Storage(String),
+}
+
+/// No wall clock on the frame, every file gets this date
+struct FixedTime;
+
+impl TimeSource for FixedTime {
+    fn get_timestamp(&self) -> Timestamp {
+        Timestamp {
+            year_since_1970: 55,
+            zero_indexed_month: 0,
+            zero_indexed_day: 0,
+            hours: 0,
+            minutes: 0,
+            seconds: 0,
+        }
+    }
+}
+
+pub struct SdCard<D: BlockDevice> {
+    volumes: VolumeManager<D, FixedTime, 4, 2, 1>,
+    volume: RawVolume,
+}
+
+impl<D: BlockDevice> SdCard<D> {
+    /// First FAT partition on the card
+    pub fn open(device: D) -> Result<Self, Error> {
+        let mut volumes = VolumeManager::new(device, FixedTime);
+        let volume = volumes
+            .open_raw_volume(VolumeIdx(0))
+            .map_err(storage_error)?;
+        Ok(Self { volumes, volume })
+    }
+
+    /// Walks `path` from the root, e.g. `PHOTOS/FRAME`. Missing folders are made when `create` is set
+    fn open_dir(&mut self, path: &str, create: bool) -> Result<RawDirectory, Error> {
+        let mut dir = self
+            .volumes
+            .open_root_dir(self.volume)
+            .map_err(storage_error)?;
+
+        for name in path.split('/').filter(|name| !name.is_empty()) {
+            if create {
+                // Already there is fine, it's the open below that has to work
+                let _ = self.volumes.make_dir_in_dir(dir, name);
+            }
+
+            let next = self.volumes.open_dir(dir, name);
+            let _ = self.volumes.close_dir(dir);
+            dir = next.map_err(|e| Error::Storage(format!("No folder {}: {:?}", name, e)))?;
+        }
+
+        Ok(dir)
+    }
+
+    /// Short names of the JPEGs in `path`
+    pub fn list_jpegs(&mut self, path: &str) -> Result<Vec<String>, Error> {
+        let dir = self.open_dir(path, false)?;
+
+        let mut names = Vec::new();
+        let result = self.volumes.iterate_dir(dir, |entry| {
+            if !entry.attributes.is_directory() && is_jpeg(&entry.name) {
+                names.push(entry.name.to_string());
+            }
+        });
+        let _ = self.volumes.close_dir(dir);
+        result.map_err(storage_error)?;
+
+        Ok(names)
+    }
+
+    /// Whether `name` is a file in `path`, without listing the whole folder
+    pub fn exists(&mut self, path: &str, name: &str) -> Result<bool, Error> {
+        let dir = self.open_dir(path, false)?;
+        let file = self.volumes.open_file_in_dir(dir, name, Mode::ReadOnly);
+        let _ = self.volumes.close_dir(dir);
+        match file {
+            Ok(file) => {
+                let _ = self.volumes.close_file(file);
+                Ok(true)
+            }
+            Err(embedded_sdmmc::Error::NotFound) => Ok(false),
+            Err(e) => Err(storage_error(e)),
+        }
+    }
+
+    /// Whole file, `TooLarge` if it's over `max_len` bytes
+    pub fn read(&mut self, path: &str, name: &str, max_len: usize) -> Result<Vec<u8>, Error> {
+        let dir = self.open_dir(path, false)?;
+        let file = self.volumes.open_file_in_dir(dir, name, Mode::ReadOnly);
+        let _ = self.volumes.close_dir(dir);
+        let file = file.map_err(|e| match e {
+            embedded_sdmmc::Error::NotFound => Error::NotFound,
+            e => storage_error(e),
+        })?;
+
+        let result = self.read_file(file, max_len);
+        let _ = self.volumes.close_file(file);
+        result
+    }
+
+    fn read_file(&mut self, file: RawFile, max_len: usize) -> Result<Vec<u8>, Error> {
+        let len = self.volumes.file_length(file).map_err(storage_error)? as usize;
+        if len > max_len {
+            return Err(Error::TooLarge);
+        }
+
+        let mut data = alloc::vec![0u8; len];
+        let mut read = 0;
+        while read < len {
+            let n = self
+                .volumes
+                .read(file, &mut data[read..])
+                .map_err(storage_error)?;
+            if n == 0 {
+                break;
+            }
+            read += n;
+        }
+        data.truncate(read);
+
+        Ok(data)
+    }
+
+    /// Replaces `name` if it's already there
+    pub fn write(&mut self, path: &str, name: &str, data: &[u8]) -> Result<(), Error> {
+        let dir = self.open_dir(path, true)?;
+        let file = self
+            .volumes
+            .open_file_in_dir(dir, name, Mode::ReadWriteCreateOrTruncate);
+        let _ = self.volumes.close_dir(dir);
+        let file = file.map_err(storage_error)?;
+
+        let result = self.volumes.write(file, data).map_err(storage_error);
+        // Closing is what flushes the directory entry
+        let closed = self.volumes.close_file(file).map_err(storage_error);
+        result.and(closed)
+    }
+
+    pub fn delete(&mut self, path: &str, name: &str) -> Result<(), Error> {
+        let dir = self.open_dir(path, false)?;
+        let result = self.volumes.delete_file_in_dir(dir, name);
+        let _ = self.volumes.close_dir(dir);
+        result.map_err(storage_error)
+    }
+}
+
+fn is_jpeg(name: &ShortFileName) -> bool {
+    matches!(name.extension(), b"JPG" | b"JPE")
+}
+
+fn storage_error<E: core::fmt::Debug>(e: embedded_sdmmc::Error<E>) -> Error {
+    Error::Storage(format!("{:?}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::cell::RefCell;
+    use core::convert::Infallible;
+    use embedded_sdmmc::{Block, BlockCount, BlockIdx};
+
+    use std::io::{Cursor, Write};
+
+    /// The image in memory, writes stay in the copy
+    struct Image(RefCell<Vec<u8>>);
+
+    impl BlockDevice for Image {
+        type Error = Infallible;
+
+        fn read(&self, blocks: &mut [Block], start: BlockIdx) -> Result<(), Infallible> {
+            let image = self.0.borrow();
+            for (i, block) in blocks.iter_mut().enumerate() {
+                let offset = (start.0 as usize + i) * Block::LEN;
+                block
+                    .contents
+                    .copy_from_slice(&image[offset..offset + Block::LEN]);
+            }
+            Ok(())
+        }
+
+        fn write(&self, blocks: &[Block], start: BlockIdx) -> Result<(), Infallible> {
+            let mut image = self.0.borrow_mut();
+            for (i, block) in blocks.iter().enumerate() {
+                let offset = (start.0 as usize + i) * Block::LEN;
+                image[offset..offset + Block::LEN].copy_from_slice(&block.contents);
+            }
+            Ok(())
+        }
+
+        fn num_blocks(&self) -> Result<BlockCount, Infallible> {
+            Ok(BlockCount((self.0.borrow().len() / Block::LEN) as u32))
+        }
+    }
+
+    /// Enough 512 byte clusters for FAT16, FAT12 is too small for embedded-sdmmc
+    const SECTORS: u32 = 4400;
+
+    /// MBR with one FAT16 partition right after it. PHOTOS/ holds A.JPG, B.JPE (1500 bytes
+    /// of 0x5A), NOTES.TXT and an OLD.JPG folder with C.JPG
+    fn image() -> Vec<u8> {
+        let mut volume = Cursor::new(alloc::vec![0u8; SECTORS as usize * Block::LEN]);
+        fatfs::format_volume(
+            &mut volume,
+            fatfs::FormatVolumeOptions::new()
+                .fat_type(fatfs::FatType::Fat16)
+                .bytes_per_cluster(512)
+                .total_sectors(SECTORS),
+        )
+        .unwrap();
+
+        {
+            let fs = fatfs::FileSystem::new(&mut volume, fatfs::FsOptions::new()).unwrap();
+            let photos = fs.root_dir().create_dir("PHOTOS").unwrap();
+            let create = |name: &str, data: &[u8]| {
+                photos.create_file(name).unwrap().write_all(data).unwrap();
+            };
+            create("A.JPG", b"\xFF\xD8 first \xFF\xD9");
+            create("B.JPE", &[0x5A; 1500]);
+            create("NOTES.TXT", b"not a photo");
+            let old = photos.create_dir("OLD.JPG").unwrap();
+            old.create_file("C.JPG")
+                .unwrap()
+                .write_all(b"deeper")
+                .unwrap();
+        }
+
+        let mut image = alloc::vec![0u8; Block::LEN];
+        let partition = &mut image[446..462];
+        partition[4] = 0x06;
+        partition[8..12].copy_from_slice(&1u32.to_le_bytes());
+        partition[12..16].copy_from_slice(&SECTORS.to_le_bytes());
+        image[510..].copy_from_slice(&[0x55, 0xAA]);
+        image.extend_from_slice(volume.get_ref());
+        image
+    }
+
+    fn card() -> SdCard<Image> {
+        SdCard::open(Image(RefCell::new(image()))).unwrap()
+    }
+
+    fn sorted(mut names: Vec<String>) -> Vec<String> {
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn lists_jpegs_only() {
+        let mut card = card();
+        // Not NOTES.TXT and not the OLD.JPG folder
+        assert_eq!(
+            sorted(card.list_jpegs("PHOTOS").unwrap()),
+            ["A.JPG", "B.JPE"]
+        );
+        assert_eq!(card.list_jpegs("/PHOTOS/OLD.JPG/").unwrap(), ["C.JPG"]);
+        assert!(card.list_jpegs("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn missing_folder() {
+        let mut card = card();
+        assert!(matches!(card.list_jpegs("NOPE"), Err(Error::Storage(_))));
+        assert!(matches!(
+            card.read("NOPE", "A.JPG", 1024),
+            Err(Error::Storage(_))
+        ));
+    }
+
+    #[test]
+    fn reads_files() {
+        let mut card = card();
+        assert_eq!(
+            card.read("PHOTOS", "A.JPG", 1024).unwrap(),
+            b"\xFF\xD8 first \xFF\xD9"
+        );
+        // Spans three clusters
+        assert_eq!(card.read("PHOTOS", "B.JPE", 1500).unwrap(), [0x5A; 1500]);
+        assert_eq!(card.read("PHOTOS", "B.JPE", 1499), Err(Error::TooLarge));
+        assert_eq!(card.read("PHOTOS", "C.JPG", 1024), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn finds_files() {
+        let mut card = card();
+        assert_eq!(card.exists("PHOTOS", "A.JPG"), Ok(true));
+        assert_eq!(card.exists("PHOTOS", "C.JPG"), Ok(false));
+        assert_eq!(card.exists("PHOTOS/OLD.JPG", "C.JPG"), Ok(true));
+        assert!(matches!(
+            card.exists("NOPE", "A.JPG"),
+            Err(Error::Storage(_))
+        ));
+    }
+
+    #[test]
+    fn writes_and_overwrites() {
+        let mut card = card();
+        let photo = [0xA5; 2000];
+        card.write("CACHE", "0000BEEF.JPG", &photo).unwrap();
+        assert_eq!(card.list_jpegs("CACHE").unwrap(), ["0000BEEF.JPG"]);
+        assert_eq!(card.read("CACHE", "0000BEEF.JPG", 4096).unwrap(), photo);
+
+        // Shorter data truncates instead of leaving the old tail behind
+        card.write("CACHE", "0000BEEF.JPG", b"newer").unwrap();
+        assert_eq!(card.list_jpegs("CACHE").unwrap(), ["0000BEEF.JPG"]);
+        assert_eq!(card.read("CACHE", "0000BEEF.JPG", 4096).unwrap(), b"newer");
+
+        // Existing folders are fine too
+        card.write("PHOTOS", "D.JPG", b"d").unwrap();
+        assert_eq!(
+            sorted(card.list_jpegs("PHOTOS").unwrap()),
+            ["A.JPG", "B.JPE", "D.JPG"]
+        );
+    }
+
+    #[test]
+    fn deletes_files() {
+        let mut card = card();
+        card.delete("PHOTOS", "A.JPG").unwrap();
+        assert_eq!(card.list_jpegs("PHOTOS").unwrap(), ["B.JPE"]);
+        assert_eq!(card.read("PHOTOS", "A.JPG", 1024), Err(Error::NotFound));
+        assert!(matches!(
+            card.delete("PHOTOS", "A.JPG"),
+            Err(Error::Storage(_))
+        ));
+    }
+}
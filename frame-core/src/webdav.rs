@@ -0,0 +1,152 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+
+use crate::photo::{self, MAX_PHOTOS, PhotoEntry};
+use crate::xml;
+
+// WebDAV PROPFIND multistatus listings
+
+/// JPEGs in a multistatus body, the folder itself and subfolders are skipped
+pub fn parse_listing(data: &str) -> Vec<PhotoEntry> {
+    xml::elements(data, "response")
+        .filter(|response| xml::first(response, "collection").is_none())
+        .filter_map(|response| {
+            let href = xml::unescape(xml::first(response, "href")?);
+            let content_type = xml::first(response, "getcontenttype").unwrap_or("");
+            is_jpeg(&href, content_type).then(|| PhotoEntry {
+                // hrefs stay encoded since that's what the server wants back
+                name: href.rsplit('/').next().map(percent_decode),
+                id: href,
+                key: None,
+                taken_at: None,
+                focus: None,
+            })
+        })
+        .take(MAX_PHOTOS)
+        .collect()
+}
+
+/// Servers don't always send a content type, fall back to the extension
+fn is_jpeg(href: &str, content_type: &str) -> bool {
+    if !content_type.is_empty() {
+        return content_type.eq_ignore_ascii_case("image/jpeg");
+    }
+
+    photo::is_jpeg(href)
+}
+
+/// `%20` and friends back to what they stand for, a `%` that isn't an escape stays as it is
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes
+            .get(i + 1..i + 3)
+            // `from_str_radix` alone would take `+1`
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escape) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// hrefs are usually an absolute path on the server but can be a whole URL
+pub fn file_url(folder: &str, href: &str) -> Option<String> {
+    let url = url::Url::parse(folder).ok()?.join(href).ok()?;
+    Some(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // PROPFIND depth 1 on a Nextcloud folder with `PROPFIND_BODY`
+    const NEXTCLOUD: &str = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:s="http://sabredav.org/ns" xmlns:oc="http://owncloud.org/ns" xmlns:nc="http://nextcloud.org/ns">
+ <d:response>
+  <d:href>/remote.php/dav/files/alice/Frame/</d:href>
+  <d:propstat>
+   <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+  <d:propstat>
+   <d:prop><d:getcontenttype/></d:prop>
+   <d:status>HTTP/1.1 404 Not Found</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/files/alice/Frame/Beach%20day.jpg</d:href>
+  <d:propstat>
+   <d:prop><d:getcontenttype>image/jpeg</d:getcontenttype><d:resourcetype/></d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/files/alice/Frame/scan.png</d:href>
+  <d:propstat>
+   <d:prop><d:getcontenttype>image/png</d:getcontenttype><d:resourcetype/></d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/files/alice/Frame/Old/</d:href>
+  <d:propstat>
+   <d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+ <d:response>
+  <d:href>/remote.php/dav/files/alice/Frame/IMG_0042.JPEG</d:href>
+  <d:propstat>
+   <d:prop><d:resourcetype/></d:prop>
+   <d:status>HTTP/1.1 200 OK</d:status>
+  </d:propstat>
+ </d:response>
+</d:multistatus>"#;
+
+    #[test]
+    fn nextcloud_listing() {
+        let entries = parse_listing(NEXTCLOUD);
+        let ids: Vec<&str> = entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(
+            ids,
+            [
+                "/remote.php/dav/files/alice/Frame/Beach%20day.jpg",
+                "/remote.php/dav/files/alice/Frame/IMG_0042.JPEG"
+            ]
+        );
+        assert_eq!(entries[0].name.as_deref(), Some("Beach day.jpg"));
+        assert_eq!(entries[1].name.as_deref(), Some("IMG_0042.JPEG"));
+    }
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(percent_decode("Caf%C3%A9%20%281%29.jpg"), "Café (1).jpg");
+        assert_eq!(percent_decode("100%.jpg"), "100%.jpg");
+        assert_eq!(percent_decode("%zz%+1%2"), "%zz%+1%2");
+    }
+
+    #[test]
+    fn file_urls() {
+        let folder = "https://cloud.example.com/remote.php/dav/files/alice/Frame";
+        assert_eq!(
+            file_url(folder, "/remote.php/dav/files/alice/Frame/a.jpg").unwrap(),
+            "https://cloud.example.com/remote.php/dav/files/alice/Frame/a.jpg"
+        );
+        assert_eq!(
+            file_url(folder, "https://other.example.com/a.jpg").unwrap(),
+            "https://other.example.com/a.jpg"
+        );
+    }
+}
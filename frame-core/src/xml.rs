@@ -0,0 +1,207 @@
+use alloc::string::String;
+
+// Just enough XML for WebDAV multistatus and S3 listings. No DTDs, CDATA or nesting of
+// same named elements, which none of those responses use
+
+/// Contents of every `name` element, namespace prefixes are ignored so `d:href` and `D:href` both match `href`
+pub fn elements<'a>(xml: &'a str, name: &'a str) -> Elements<'a> {
+    Elements { rest: xml, name }
+}
+
+/// Contents of the first `name` element
+pub fn first<'a>(xml: &'a str, name: &'a str) -> Option<&'a str> {
+    elements(xml, name).next()
+}
+
+pub struct Elements<'a> {
+    rest: &'a str,
+    name: &'a str,
+}
+
+impl<'a> Iterator for Elements<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            let start = self.rest.find('<')?;
+            let tag = &self.rest[start + 1..];
+            let end = tag.find('>')?;
+            let inside = &tag[..end];
+            let after = &tag[end + 1..];
+
+            let qualified = inside
+                .split(|c: char| c.is_ascii_whitespace() || c == '/')
+                .next()
+                .unwrap_or("");
+            let local = qualified.rsplit(':').next().unwrap_or("");
+
+            // Closing tags, `<?xml` and comments
+            if qualified.is_empty() || local != self.name || inside.starts_with(['/', '?', '!']) {
+                self.rest = after;
+                continue;
+            }
+
+            if inside.ends_with('/') {
+                self.rest = after;
+                return Some("");
+            }
+
+            let close = alloc::format!("</{}>", qualified);
+            let Some(content_len) = after.find(close.as_str()) else {
+                self.rest = "";
+                return None;
+            };
+
+            self.rest = &after[content_len + close.len()..];
+            return Some(after[..content_len].trim());
+        }
+    }
+}
+
+/// Replaces the predefined entities, e.g. `&amp;` in an href
+pub fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let entity = [
+            ("&amp;", '&'),
+            ("&lt;", '<'),
+            ("&gt;", '>'),
+            ("&quot;", '"'),
+            ("&apos;", '\''),
+        ]
+        .into_iter()
+        .find(|(entity, _)| rest.starts_with(entity));
+
+        match entity {
+            Some((entity, c)) => {
+                out.push(c);
+                rest = &rest[entity.len()..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec::Vec;
+
+    // PROPFIND depth 1 on Synology WebDAV Server, which is Apache mod_dav
+    const MULTISTATUS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<D:multistatus xmlns:D="DAV:" xmlns:ns0="DAV:">
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/photo/Frame/</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype><D:collection/></lp1:resourcetype>
+<D:getcontenttype>httpd/unix-directory</D:getcontenttype>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/photo/Frame/Tom%20&amp;%20Jerry.jpg</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype/>
+<D:getcontenttype>image/jpeg</D:getcontenttype>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+<D:response xmlns:lp1="DAV:" xmlns:lp2="http://apache.org/dav/props/">
+<D:href>/photo/Frame/notes.txt</D:href>
+<D:propstat>
+<D:prop>
+<lp1:resourcetype/>
+<D:getcontenttype>text/plain</D:getcontenttype>
+</D:prop>
+<D:status>HTTP/1.1 200 OK</D:status>
+</D:propstat>
+</D:response>
+</D:multistatus>
+"#;
+
+    #[test]
+    fn multistatus_responses() {
+        let responses: Vec<&str> = elements(MULTISTATUS, "response").collect();
+        assert_eq!(responses.len(), 3);
+
+        let hrefs: Vec<&str> = responses.iter().filter_map(|r| first(r, "href")).collect();
+        assert_eq!(
+            hrefs,
+            [
+                "/photo/Frame/",
+                "/photo/Frame/Tom%20&amp;%20Jerry.jpg",
+                "/photo/Frame/notes.txt"
+            ]
+        );
+
+        // `<D:collection/>` only on the folder itself
+        assert_eq!(first(responses[0], "collection"), Some(""));
+        assert_eq!(first(responses[1], "collection"), None);
+        assert_eq!(first(responses[1], "resourcetype"), Some(""));
+
+        assert_eq!(first(responses[1], "getcontenttype"), Some("image/jpeg"));
+        assert_eq!(first(responses[2], "status"), Some("HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn prefixes_are_ignored() {
+        let xml = "<d:a>1</d:a><!-- <a> --><D:a>2</D:a><a>3</a><ab>4</ab>";
+        let found: Vec<&str> = elements(xml, "a").collect();
+        assert_eq!(found, ["1", "2", "3"]);
+    }
+
+    #[test]
+    fn s3_listing() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<ListBucketResult xmlns="http://s3.amazonaws.com/doc/2006-03-01/">
+<Name>frame</Name><Prefix>2024/</Prefix><KeyCount>2</KeyCount><MaxKeys>1000</MaxKeys>
+<IsTruncated>true</IsTruncated>
+<Contents><Key>2024/a.jpg</Key><Size>1024</Size></Contents>
+<Contents><Key>2024/b &amp; c.jpg</Key><Size>2048</Size></Contents>
+<NextContinuationToken>1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=</NextContinuationToken>
+</ListBucketResult>"#;
+
+        let keys: Vec<String> = elements(xml, "Contents")
+            .filter_map(|contents| first(contents, "Key").map(unescape))
+            .collect();
+        assert_eq!(keys, ["2024/a.jpg", "2024/b & c.jpg"]);
+        assert_eq!(first(xml, "IsTruncated"), Some("true"));
+        assert_eq!(
+            first(xml, "NextContinuationToken"),
+            Some("1ueGcxLPRx1Tr/XYExHnhbYLgveDs2J/wm36Hy4vbOwM=")
+        );
+    }
+
+    #[test]
+    fn unclosed_element() {
+        assert_eq!(first("<a>1</a><b>2", "b"), None);
+        assert_eq!(elements("<a>1</a><a>2", "a").count(), 1);
+    }
+
+    #[test]
+    fn unescaping() {
+        assert_eq!(
+            unescape("/photo/Frame/Tom%20&amp;%20Jerry.jpg"),
+            "/photo/Frame/Tom%20&%20Jerry.jpg"
+        );
+        assert_eq!(unescape("&lt;&gt;&quot;&apos;&amp;amp;"), "<>\"'&amp;");
+        // Anything else is left alone
+        assert_eq!(unescape("a & b &nbsp; c&"), "a & b &nbsp; c&");
+        assert_eq!(unescape("plain"), "plain");
+    }
+}
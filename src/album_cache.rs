@@ -0,0 +1,98 @@
+use alloc::vec::Vec;
+use defmt::{info, warn};
+use embedded_storage::{ReadStorage, Storage};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+extern crate alloc;
+
+// Keeps the album listing in the `frame` flash partition so most wakes only need the thumbnail request.
+// Layout: 16 byte header (magic, fingerprint, refreshed at, length) followed by the listing as JSON
+
+const MAGIC: u32 = 0x4143_4831;
+const HEADER_LEN: usize = 16;
+
+pub struct AlbumCache<S> {
+    storage: S,
+    /// Changes when the album config changes so we don't show photos from the old one
+    fingerprint: u32,
+    /// Seconds on the RTC clock, which keeps counting through deep sleep
+    now_secs: u32,
+}
+
+impl<S: Storage> AlbumCache<S> {
+    pub fn new(storage: S, fingerprint: u32, now_secs: u32) -> Self {
+        Self {
+            storage,
+            fingerprint,
+            now_secs,
+        }
+    }
+
+    /// Cached listing if it's for the same album and younger than `max_age_secs`
+    pub fn load<T: DeserializeOwned>(&mut self, max_age_secs: u32) -> Option<T> {
+        let mut header = [0u8; HEADER_LEN];
+        if self.storage.read(0, &mut header).is_err() {
+            warn!("[CACHE] Could not read header");
+            return None;
+        }
+
+        let word = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        let (magic, fingerprint, refreshed_at, len) = (word(0), word(1), word(2), word(3));
+
+        if magic != MAGIC || fingerprint != self.fingerprint {
+            info!("[CACHE] Empty or for another album");
+            return None;
+        }
+
+        // The RTC clock starts over after a power loss, treat that as stale too
+        let age = self.now_secs.checked_sub(refreshed_at)?;
+        if age > max_age_secs {
+            info!("[CACHE] Stale, {}s old", age);
+            return None;
+        }
+
+        if len as usize > self.storage.capacity() - HEADER_LEN {
+            return None;
+        }
+
+        let mut data = alloc::vec![0u8; len as usize];
+        if self.storage.read(HEADER_LEN as u32, &mut data).is_err() {
+            warn!("[CACHE] Could not read listing");
+            return None;
+        }
+
+        let listing = serde_json::from_slice(&data).ok()?;
+        info!("[CACHE] Using cached listing, {}s old", age);
+        Some(listing)
+    }
+
+    pub fn store<T: Serialize>(&mut self, listing: &T) {
+        let Ok(json) = serde_json::to_vec(listing) else {
+            return;
+        };
+
+        if json.len() > self.storage.capacity() - HEADER_LEN {
+            warn!("[CACHE] Listing is too big for the partition");
+            return;
+        }
+
+        let mut data = Vec::with_capacity(HEADER_LEN + json.len());
+        for word in [MAGIC, self.fingerprint, self.now_secs, json.len() as u32] {
+            data.extend_from_slice(&word.to_le_bytes());
+        }
+        data.extend_from_slice(&json);
+
+        match self.storage.write(0, &data) {
+            Ok(()) => info!("[CACHE] Stored {} bytes", data.len()),
+            Err(_) => warn!("[CACHE] Could not write listing"),
+        }
+    }
+
+    /// Next wake will list the album again
+    pub fn invalidate(&mut self) {
+        if self.storage.write(0, &[0u8; HEADER_LEN]).is_err() {
+            warn!("[CACHE] Could not invalidate");
+        }
+    }
+}
@@ -66,7 +66,10 @@ pub async fn get_charge_state(
 
     info!("[BAT] {} (v{})", percent, battery_mv);
 
-    return ChargeState { percent, volts: battery_mv };
+    return ChargeState {
+        percent,
+        volts: battery_mv,
+    };
 }
 
 fn voltage_to_battery_percentage(mv: u16) -> i8 {
@@ -9,32 +9,51 @@
 
 use alloc::boxed::Box;
 use alloc::format;
-use defmt::info;
+use core::cell::RefCell;
+use core::sync::atomic::{AtomicI32, Ordering};
+use defmt::{Debug2Format, error, info, warn};
 use embassy_executor::Spawner;
-use embassy_time::{Duration, Timer};
-use embedded_graphics::image::{Image, ImageRaw};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
 use embedded_graphics::mono_font::MonoTextStyle;
 use embedded_graphics::mono_font::iso_8859_14::FONT_10X20;
 use embedded_graphics::prelude::*;
 use embedded_graphics::primitives::{PrimitiveStyleBuilder, Rectangle};
 use embedded_graphics::text::{Alignment, Text};
-use embedded_hal_bus::spi::ExclusiveDevice;
+use embedded_hal_bus::spi::RefCellDevice;
 use epd_waveshare::color::HexColor;
 use epd_waveshare::epd7in3e::{Display7in3e, Epd7in3e};
 use epd_waveshare::prelude::WaveshareDisplay;
+use esp_bootloader_esp_idf::partitions;
 use esp_hal::clock::CpuClock;
 use esp_hal::delay::Delay;
 use esp_hal::gpio::{Input, InputConfig, Pull};
 use esp_hal::gpio::{Level, Output, OutputConfig};
 use esp_hal::interrupt::software::SoftwareInterruptControl;
+use esp_hal::peripherals::GPIO3;
+use esp_hal::rtc_cntl::{Rtc, wakeup_cause};
 use esp_hal::spi::master::Spi;
+use esp_hal::system::SleepSource;
+use esp_hal::time::Rate;
 use esp_hal::timer::timg::TimerGroup;
 use esp_radio::wifi::WifiController;
+use esp_storage::FlashStorage;
+use synology_photo_frame::album_cache::AlbumCache;
 use synology_photo_frame::battery::get_charge_state;
-use synology_photo_frame::images::{floyd_steinberg_dither, mitchell_upscale};
-use synology_photo_frame::synology::get_image;
-use zune_jpeg::JpegDecoder;
-use zune_jpeg::zune_core::bytestream::ZCursor;
+use synology_photo_frame::home_assistant::{Broker, Commands, Status};
+use synology_photo_frame::image_url::ImageUrlSource;
+use synology_photo_frame::images::{
+    Anchor, ColorSpace, DecodedImage, Dither, DitherOptions, Filter, Focus, Mat, Palette,
+    RenderOptions, ScaleMode, Tone, palette_color, render,
+};
+use synology_photo_frame::immich::ImmichSource;
+use synology_photo_frame::notify::{self, LowBattery, Service};
+use synology_photo_frame::s3::S3Source;
+use synology_photo_frame::sd_card::{SdCached, SdCard, SdFolder};
+use synology_photo_frame::source::{self, Backend, get_photo};
+use synology_photo_frame::state;
+use synology_photo_frame::synology::{FileStationSource, Flavor, SynologySource};
+use synology_photo_frame::webdav::WebDavSource;
+use synology_photo_frame::{config, http};
 use {esp_backtrace as _, esp_println as _};
 extern crate alloc;
 
@@ -42,7 +61,27 @@ extern crate alloc;
 // For more information see: <https://docs.espressif.com/projects/esp-idf/en/stable/esp32/api-reference/system/app_image_format.html#application-description>
 esp_bootloader_esp_idf::esp_app_desc!();
 
-static NETWORK_RESOURCES: static_cell::ConstStaticCell<embassy_net::StackResources<3>> =
+const SLEEP_DURATION: core::time::Duration = core::time::Duration::from_hours(9);
+
+/// Everything network related has to be done by then or we show an error and go back to sleep
+const FETCH_DEADLINE: Duration = Duration::from_secs(90);
+
+/// Where photos fetched over the network are kept on the SD card
+const SD_CACHE_FOLDER: &str = "CACHE";
+/// Battery is nearly empty, don't burn what's left on a notification that won't go through
+const NOTIFY_DEADLINE: Duration = Duration::from_secs(30);
+/// Has to climb this far over the threshold before the next low battery notification,
+/// so a battery hovering around it doesn't notify every time the button is pressed
+const LOW_BATTERY_HYSTERESIS: i16 = 10;
+
+type FlashCache<'a> = AlbumCache<partitions::FlashRegion<'a, FlashStorage<'a>>>;
+type SharedSpi<'a> = RefCellDevice<'a, Spi<'static, esp_hal::Blocking>, Output<'static>, Delay>;
+
+/// Last signal strength `wifi_task` saw, 0 until connected
+static RSSI: AtomicI32 = AtomicI32::new(0);
+
+// DHCP, DNS, the HTTP connection, NTP and MQTT
+static NETWORK_RESOURCES: static_cell::ConstStaticCell<embassy_net::StackResources<5>> =
     static_cell::ConstStaticCell::new(embassy_net::StackResources::new());
 
 #[allow(
@@ -81,17 +120,16 @@ async fn main(spawner: Spawner) -> ! {
 
     info!("Embassy initialized!");
 
-    let charge_state = get_charge_state(peripherals.ADC1, peripherals.GPIO1, peripherals.GPIO21).await;
+    let charge_state =
+        get_charge_state(peripherals.ADC1, peripherals.GPIO1, peripherals.GPIO21).await;
 
-    let epd_spi_bus = Spi::new(
-        peripherals.SPI2,
-        esp_hal::spi::master::Config::default()
-            .with_frequency(esp_hal::time::Rate::from_mhz(20))
-            .with_mode(esp_hal::spi::Mode::_0),
-    )
-    .unwrap()
-    .with_sck(peripherals.GPIO7)
-    .with_mosi(peripherals.GPIO9);
+    let epd_spi_bus = Spi::new(peripherals.SPI2, spi_config(Rate::from_mhz(20)))
+        .unwrap()
+        .with_sck(peripherals.GPIO7)
+        .with_mosi(peripherals.GPIO9)
+        .with_miso(peripherals.GPIO8);
+    // Display and SD card share the bus, each with its own chip select
+    let epd_spi_bus = RefCell::new(epd_spi_bus);
 
     info!("Bus ");
     let mut delay = Delay::new();
@@ -104,7 +142,7 @@ async fn main(spawner: Spawner) -> ! {
         InputConfig::default().with_pull(Pull::Up),
     );
 
-    let mut epd_spi_dev = ExclusiveDevice::new(epd_spi_bus, screen_cs, delay).unwrap();
+    let mut epd_spi_dev = RefCellDevice::new(&epd_spi_bus, screen_cs, delay).unwrap();
 
     info!("Screen pins");
 
@@ -122,8 +160,13 @@ async fn main(spawner: Spawner) -> ! {
 
     let mut display = Box::new(Display7in3e::default());
 
+    let mut rtc_state = state::load();
+    // Checked to be at most 100 in `config`, the battery's `i8` can go negative
+    let low_battery = config::LOW_BATTERY_PERCENT as i16;
+    let percent = i16::from(charge_state.percent);
+
     // Prevent battery damage
-    if charge_state.percent <= 5 {
+    if percent <= low_battery {
         Text::with_alignment(
             format!(
                 "I NEEDS A CHARGE\nBATTERY IS {}% v{:.2}\nPRESS RESET TO UPDATE",
@@ -146,19 +189,298 @@ async fn main(spawner: Spawner) -> ! {
 
         epd7in3e.sleep(&mut epd_spi_dev, &mut delay).unwrap();
 
-        let wakeup_pins: &mut [(
-            &mut dyn esp_hal::gpio::RtcPin,
-            esp_hal::rtc_cntl::sleep::WakeupLevel,
-        )] = &mut [(
-            &mut gpio_btn_reset,
-            esp_hal::rtc_cntl::sleep::WakeupLevel::Low,
-        )];
-        let pin_wake_source = esp_hal::rtc_cntl::sleep::RtcioWakeupSource::new(wakeup_pins);
+        if let Some(service) = Service::parse(config::NOTIFY_SERVICE)
+            && !rtc_state.low_battery_notified
+        {
+            let notified = with_timeout(NOTIFY_DEADLINE, async {
+                let (net_stack, seed) = start_network(spawner, peripherals.WIFI).await;
+                let mut client = http::new_client(net_stack, seed);
+                notify::low_battery(
+                    &mut client,
+                    &service,
+                    config::NOTIFY_URL,
+                    config::NOTIFY_TOKEN,
+                    &LowBattery {
+                        device: config::DEVICE_NAME,
+                        percent: charge_state.percent,
+                        mv: charge_state.volts,
+                    },
+                )
+                .await
+            })
+            .await;
+
+            match notified {
+                Ok(Ok(())) => {
+                    rtc_state.low_battery_notified = true;
+                    state::store(&rtc_state);
+                }
+                Ok(Err(e)) => error!("[BAT] Could not send notification: {:?}", e),
+                Err(_) => error!("[BAT] Notification timed out"),
+            }
+        }
 
         info!("[BAT] -> Going for long sleep");
-        rtc.sleep_deep(&[&pin_wake_source]);
+        deep_sleep(&mut rtc, &mut gpio_btn_reset, None);
+    }
+
+    let mut sd_card = if config::PHOTO_SOURCE == "sdcard" || config::SD_CACHE {
+        let sd_cs = Output::new(peripherals.GPIO14, Level::High, OutputConfig::default());
+        open_sd_card(&epd_spi_bus, sd_cs, delay)
+    } else {
+        None
+    };
+
+    // Charged again, the next low battery gets its own notification
+    let charged = percent > low_battery.saturating_add(LOW_BATTERY_HYSTERESIS);
+    if rtc_state.low_battery_notified && charged {
+        rtc_state.low_battery_notified = false;
+        state::store(&rtc_state);
+    }
+
+    let fingerprint = config::source_fingerprint();
+
+    // Logging in again with the same bad password gets the whole IP blocked by DSM.
+    // The rejection screen is still up so just go back to sleep until someone presses the button
+    if rtc_state.credentials_rejected(fingerprint) && matches!(wakeup_cause(), SleepSource::Timer) {
+        info!(
+            "[SRC] Credentials were rejected with {}, waiting for the button",
+            rtc_state.rejected_code
+        );
+        deep_sleep(&mut rtc, &mut gpio_btn_reset, None);
+    }
+
+    let mut wake_after = Some(SLEEP_DURATION);
+    // Broker to clear the "next" press on once the new photo is on the panel
+    let mut next_pressed = None;
+
+    let result = if config::PHOTO_SOURCE == "sdcard" {
+        // Fully offline, WiFi stays off
+        match sd_card.as_mut() {
+            Some(card) => {
+                get_photo(
+                    &mut SdFolder::new(card, config::SD_FOLDER),
+                    Instant::now() + FETCH_DEADLINE,
+                    None::<&mut FlashCache>,
+                    0,
+                )
+                .await
+            }
+            None => Err(source::Error::Storage),
+        }
+    } else {
+        let (net_stack, seed) = start_network(spawner, peripherals.WIFI).await;
+
+        let broker = (!config::MQTT_HOST.is_empty()).then(|| Broker {
+            stack: net_stack,
+            host: config::MQTT_HOST,
+            port: config::MQTT_PORT as u16,
+            credentials: config::MQTT_USER.map(|user| (user, config::MQTT_PASS)),
+            topic: config::MQTT_TOPIC,
+        });
+
+        let commands = match &broker {
+            Some(broker) => broker.read_commands().await.unwrap_or_else(|e| {
+                warn!("[HA] Could not read commands: {:?}", e);
+                Commands::default()
+            }),
+            None => Commands::default(),
+        };
+
+        // Whatever is on the panel stays there, e-paper doesn't need power to keep it
+        if commands.paused && !commands.next {
+            info!("[HA] Paused, keeping the current photo");
+            if let Some(broker) = &broker {
+                let status = Status {
+                    battery_percent: charge_state.percent,
+                    battery_mv: charge_state.volts,
+                    rssi: rssi(),
+                    photo: None,
+                    error: None,
+                };
+                if let Err(e) = broker.publish(&status, &commands).await {
+                    warn!("[HA] Could not publish: {:?}", e);
+                }
+            }
+            epd7in3e.sleep(&mut epd_spi_dev, &mut delay).unwrap();
+            deep_sleep(&mut rtc, &mut gpio_btn_reset, wake_after);
+        }
+
+        // Album picked in Home Assistant gets its own cached listing
+        let cache_fingerprint = match &commands.album {
+            Some(album) => state::fingerprint(&[&format!("{}", fingerprint), album]),
+            None => fingerprint,
+        };
+
+        let mut flash = FlashStorage::new(peripherals.FLASH);
+        let mut partition_table_buf = alloc::vec![0u8; partitions::PARTITION_TABLE_MAX_LEN];
+        let partition_table =
+            partitions::read_partition_table(&mut flash, &mut partition_table_buf);
+        let cache_partition = match &partition_table {
+            Ok(table) => table
+                .find_partition(partitions::PartitionType::Data(
+                    partitions::DataPartitionSubType::Undefined,
+                ))
+                .ok()
+                .flatten(),
+            Err(_) => None,
+        };
+        let mut album_cache = match cache_partition {
+            Some(partition) => Some(AlbumCache::new(
+                partition.as_embedded_storage(&mut flash),
+                cache_fingerprint,
+                rtc.time_since_boot().as_secs() as u32,
+            )),
+            None => {
+                warn!("[CACHE] No frame partition, album will be listed every wake");
+                None
+            }
+        };
+
+        let deadline = Instant::now() + FETCH_DEADLINE;
+        let max_age_secs = config::ALBUM_CACHE_HOURS * 60 * 60;
+
+        // THIS HAS TO BE DONE ASAP BECAUSE THERE'S SOME BULLSH*T BEHAVIOR IF THE STACK SIZE IS OVER 50% AND IT TRIES TO MAKE A COPY OF IT FOR SOME DUMB ASS REASON
+        let album = commands.album.as_deref();
+        let result = match (build_source(net_stack, seed, album), sd_card.as_mut()) {
+            (Some(backend), Some(card)) if config::SD_CACHE => {
+                let mut backend = SdCached::new(
+                    backend,
+                    card,
+                    SD_CACHE_FOLDER,
+                    config::SD_CACHE_MAX as usize,
+                );
+                get_photo(&mut backend, deadline, album_cache.as_mut(), max_age_secs).await
+            }
+            (Some(mut backend), _) => {
+                get_photo(&mut backend, deadline, album_cache.as_mut(), max_age_secs).await
+            }
+            (None, _) => {
+                error!("[SRC] Unknown PHOTO_SOURCE {}", config::PHOTO_SOURCE);
+                Err(source::Error::UnknownSource)
+            }
+        };
+
+        // Network let us down, show one we saved on an earlier wake instead
+        let result = match (result, sd_card.as_mut()) {
+            (Err(e), Some(card))
+                if config::SD_CACHE
+                    && !matches!(
+                        e,
+                        source::Error::CredentialsRejected { .. } | source::Error::UnknownSource
+                    ) =>
+            {
+                warn!("[SD] Showing a cached photo instead: {:?}", e);
+                get_photo(
+                    &mut SdFolder::new(card, SD_CACHE_FOLDER),
+                    Instant::now() + FETCH_DEADLINE,
+                    None::<&mut FlashCache>,
+                    0,
+                )
+                .await
+                .map_err(|_| e)
+            }
+            (result, _) => result,
+        };
+
+        if let Some(broker) = &broker {
+            let status = Status {
+                battery_percent: charge_state.percent,
+                battery_mv: charge_state.volts,
+                rssi: rssi(),
+                photo: result
+                    .as_ref()
+                    .ok()
+                    .map(|photo| photo.entry.name.as_deref().unwrap_or(&photo.entry.id)),
+                error: result.as_ref().err().map(source::Error::message),
+            };
+            if let Err(e) = broker.publish(&status, &commands).await {
+                warn!("[HA] Could not publish: {:?}", e);
+            }
+        }
+
+        if commands.next && result.is_ok() {
+            next_pressed = broker;
+        }
+
+        result
+    };
+
+    let rejected_code = match result {
+        Err(source::Error::CredentialsRejected { code, .. }) => Some(code),
+        _ => None,
+    };
+    if rtc_state.rejected_credentials != 0 || rejected_code.is_some() {
+        rtc_state.rejected_credentials = rejected_code.map_or(0, |_| fingerprint);
+        rtc_state.rejected_code = rejected_code.unwrap_or(0);
+        state::store(&rtc_state);
+    }
+
+    match result {
+        Ok(photo) => draw_photo(display.as_mut(), &photo.image, photo.entry.focus),
+        Err(source::Error::CredentialsRejected { code, message }) => {
+            draw_message(
+                display.as_mut(),
+                format!(
+                    "SERVER REJECTED THE LOGIN (ERROR {})\n{}\nNOT TRYING AGAIN SO THIS IP DOESN'T GET BLOCKED\nFIX THE CONFIG OR PRESS THE BUTTON TO RETRY",
+                    code, message
+                )
+                .as_str(),
+            );
+            wake_after = None;
+        }
+        Err(e) => {
+            error!("[PIC] No photo this time: {:?}", e);
+            draw_message(
+                display.as_mut(),
+                format!("COULD NOT GET A PHOTO\n{}", e.message()).as_str(),
+            );
+        }
     }
 
+    Rectangle::new(Point::new(50, 10), Size::new(50, 20))
+        .into_styled(
+            PrimitiveStyleBuilder::new()
+                .fill_color(if charge_state.percent <= 10 {
+                    HexColor::Red
+                } else {
+                    HexColor::Black
+                })
+                .build(),
+        )
+        .draw(display.as_mut())
+        .unwrap();
+
+    Text::with_alignment(
+        format!("{:?}%", charge_state.percent).as_str(),
+        Point::new(50, 25),
+        MonoTextStyle::new(&FONT_10X20, HexColor::White),
+        Alignment::Left,
+    )
+    .draw(display.as_mut())
+    .unwrap();
+
+    epd7in3e
+        .update_and_display_frame(&mut epd_spi_dev, display.buffer(), &mut delay)
+        .unwrap();
+
+    if let Some(broker) = next_pressed
+        && let Err(e) = broker.clear_next().await
+    {
+        warn!("[HA] Could not clear next: {:?}", e);
+    }
+
+    epd7in3e.sleep(&mut epd_spi_dev, &mut delay).unwrap();
+
+    info!("[ESP] Going to deep sleep :)");
+    deep_sleep(&mut rtc, &mut gpio_btn_reset, wake_after);
+}
+
+/// Brings WiFi up and waits for DHCP. The seed is for TLS
+async fn start_network(
+    spawner: Spawner,
+    wifi: esp_hal::peripherals::WIFI<'static>,
+) -> (embassy_net::Stack<'static>, u64) {
     const SSID: &str = env!("WIFI_SSID");
     const PASSWORD: &str = env!("WIFI_PASSWORD");
 
@@ -170,7 +492,7 @@ async fn main(spawner: Spawner) -> ! {
             .with_password(PASSWORD.into()),
     );
     let (wifi_controller, interfaces) = esp_radio::wifi::new(
-        peripherals.WIFI,
+        wifi,
         esp_radio::wifi::ControllerConfig::default().with_initial_config(station_config),
     )
     .expect("Failed to initialize Wi-Fi controller");
@@ -194,99 +516,288 @@ async fn main(spawner: Spawner) -> ! {
     net_stack.wait_config_up().await;
     info!("[NET] Network config up! {:?}", net_stack.config_v4());
 
-    const SYN_BASE: &str = env!("SYN_BASE");
-    const SYN_USER: &str = env!("SYN_USER");
-    const SYN_PASS: &str = env!("SYN_PASS");
-    const SYN_ALBUM: &str = env!("SYN_ALBUM");
-    // THIS HAS TO BE DONE ASAP BECAUSE THERE'S SOME BULLSH*T BEHAVIOR IF THE STACK SIZE IS OVER 50% AND IT TRIES TO MAKE A COPY OF IT FOR SOME DUMB ASS REASON
-    let image_bytes = get_image(net_stack, SYN_BASE, SYN_USER, SYN_PASS, SYN_ALBUM).await;
-
-    let cursor = ZCursor::new(image_bytes);
-    let mut decoder = JpegDecoder::new(cursor);
-
-    let pixels = decoder.decode().expect("is fucked");
-    let img_info = decoder.info().expect("Missing JPEG info");
-
-    let (resized, resized_width, _resized_height) = mitchell_upscale(
-        pixels,
-        img_info.width.into(),
-        img_info.height.into(),
-        epd7in3e.width() as usize,
-        epd7in3e.height() as usize,
-    );
+    (net_stack, seed)
+}
 
-    let dithered_bytes = floyd_steinberg_dither(resized_width.into(), resized);
+fn rssi() -> Option<i32> {
+    let rssi = RSSI.load(Ordering::Relaxed);
+    (rssi != 0).then_some(rssi)
+}
 
-    // I think HexColor should be embedded_graphics_core::pixelcolor::raw::RawU4 because it causes this weird bug
-    // image size: 600x338
-    // embedded graphics size: 600x676
-    info!(
-        "[PIC] image size: {:?}x{:?}",
-        img_info.width, img_info.height
-    );
+fn spi_config(frequency: Rate) -> esp_hal::spi::master::Config {
+    esp_hal::spi::master::Config::default()
+        .with_frequency(frequency)
+        .with_mode(esp_hal::spi::Mode::_0)
+}
 
-    // Not sure of boxing the image will do anything as it already takes in a vac but fuck it, we ball
-    let mut raw = Box::new(ImageRaw::<HexColor>::new(
-        &dithered_bytes,
-        (resized_width) as u32,
-    ));
-    let r = raw.bounding_box().size;
-    info!("[PIC] raw size {:?}x{:?}", r.width, r.height);
+/// SD card on the display's bus, `None` if there's no card or it isn't FAT
+fn open_sd_card<'a>(
+    bus: &'a RefCell<Spi<'static, esp_hal::Blocking>>,
+    cs: Output<'static>,
+    delay: Delay,
+) -> Option<SdCard<embedded_sdmmc::SdCard<SharedSpi<'a>, Delay>>> {
+    let device = RefCellDevice::new(bus, cs, delay).ok()?;
+    let card = embedded_sdmmc::SdCard::new(device, delay);
+
+    // Cards only listen at 400kHz until they're initialised
+    bus.borrow_mut()
+        .apply_config(&spi_config(Rate::from_khz(400)))
+        .ok()?;
+    let size = card.num_bytes();
+    bus.borrow_mut()
+        .apply_config(&spi_config(Rate::from_mhz(20)))
+        .ok()?;
+
+    match size {
+        Ok(bytes) => info!("[SD] {} MB card", bytes / 1_000_000),
+        Err(e) => {
+            warn!("[SD] No card: {:?}", Debug2Format(&e));
+            return None;
+        }
+    }
 
-    let size = display.size();
-    let center = Point::new((size.width as i32) / 2, (size.height as i32) / 2);
+    match SdCard::open(card) {
+        Ok(card) => {
+            info!("[SD] Volume open");
+            Some(card)
+        }
+        Err(e) => {
+            warn!("[SD] No FAT volume: {:?}", Debug2Format(&e));
+            None
+        }
+    }
+}
 
-    let image = Box::new(Image::with_center(raw.as_mut(), center));
+/// Backend picked by `PHOTO_SOURCE`, `None` if it's not one we know
+/// `album` is the one picked in Home Assistant, it replaces the configured album or folder
+fn build_source(
+    stack: embassy_net::Stack<'static>,
+    seed: u64,
+    album: Option<&str>,
+) -> Option<Backend> {
+    match config::PHOTO_SOURCE {
+        "synology" => Some(Backend::Synology(SynologySource::new(
+            http::new_client(stack, seed),
+            endpoint(config::SYN_BASE, config::SYN_PATH_PREFIX),
+            match config::SYN_API {
+                "photos" => Flavor::Photos,
+                "photostation" => Flavor::PhotoStation,
+                _ => Flavor::Auto,
+            },
+            config::SYN_USER,
+            config::SYN_PASS,
+            album.unwrap_or(config::SYN_ALBUM),
+        ))),
+        "filestation" => Some(Backend::FileStation(FileStationSource::new(
+            http::new_client(stack, seed),
+            endpoint(config::SYN_BASE, config::SYN_PATH_PREFIX),
+            config::SYN_USER,
+            config::SYN_PASS,
+            album.unwrap_or(config::SYN_FOLDER),
+            config::SYN_FOLDER_RECURSIVE,
+        ))),
+        "immich" => Some(Backend::Immich(ImmichSource::new(
+            http::new_client(stack, seed),
+            endpoint(config::IMMICH_BASE, ""),
+            config::IMMICH_API_KEY,
+            album.unwrap_or(config::IMMICH_ALBUM),
+        ))),
+        "url" => {
+            let mut endpoint = endpoint(config::IMAGE_URL, "");
+            if let Some(token) = config::IMAGE_URL_TOKEN {
+                endpoint = endpoint.with_bearer_token(token);
+            }
+            Some(Backend::ImageUrl(ImageUrlSource::new(
+                http::new_client(stack, seed),
+                endpoint,
+            )))
+        }
+        "webdav" => {
+            let mut endpoint = endpoint(config::WEBDAV_URL, "");
+            if !config::WEBDAV_USER.is_empty() {
+                let credentials = format!("{}:{}", config::WEBDAV_USER, config::WEBDAV_PASS);
+                endpoint = endpoint.with_basic_auth(&credentials);
+            }
+            Some(Backend::WebDav(WebDavSource::new(
+                http::new_client(stack, seed),
+                endpoint,
+            )))
+        }
+        "s3" => Some(Backend::S3(S3Source::new(
+            http::new_client(stack, seed),
+            stack,
+            endpoint(config::S3_ENDPOINT, ""),
+            config::S3_BUCKET,
+            album.unwrap_or(config::S3_PREFIX),
+            config::S3_REGION,
+            config::S3_ACCESS_KEY,
+            config::S3_SECRET_KEY,
+            config::NTP_SERVER,
+        ))),
+        _ => None,
+    }
+}
 
-    let s = image.bounding_box().size;
-    info!("[PIC] Embedded image size: {:?}x{:?}", s.width, s.height);
+/// Server endpoint with the reverse proxy headers from config
+fn endpoint(base: &str, path_prefix: &str) -> http::Endpoint {
+    let endpoint = http::Endpoint::new(base, path_prefix).with_headers(config::HTTP_HEADERS);
+    match config::HTTP_BASIC_AUTH {
+        Some(credentials) => endpoint.with_basic_auth(credentials),
+        None => endpoint,
+    }
+}
 
-    image.draw(display.as_mut()).unwrap();
+/// Sleeps until the button is pressed or `wake_after` passes, if given
+fn deep_sleep(
+    rtc: &mut Rtc<'static>,
+    button: &mut GPIO3<'static>,
+    wake_after: Option<core::time::Duration>,
+) -> ! {
+    let wakeup_pins: &mut [(
+        &mut dyn esp_hal::gpio::RtcPin,
+        esp_hal::rtc_cntl::sleep::WakeupLevel,
+    )] = &mut [(button, esp_hal::rtc_cntl::sleep::WakeupLevel::Low)];
+    let pin_wake_source = esp_hal::rtc_cntl::sleep::RtcioWakeupSource::new(wakeup_pins);
 
-    Rectangle::new(Point::new(50, 10), Size::new(50, 20))
+    match wake_after {
+        Some(duration) => {
+            let timer_wake_source = esp_hal::rtc_cntl::sleep::TimerWakeupSource::new(duration);
+            let wake_sources: &[&dyn esp_hal::rtc_cntl::sleep::WakeSource] =
+                &[&timer_wake_source, &pin_wake_source];
+            rtc.sleep_deep(wake_sources)
+        }
+        None => rtc.sleep_deep(&[&pin_wake_source]),
+    }
+}
+
+fn draw_photo(display: &mut Display7in3e, decoded: &DecodedImage, focus: Option<Focus>) {
+    let size = display.size();
+
+    let filter = Filter::parse(config::RESIZE_FILTER).unwrap_or_else(|| {
+        warn!(
+            "[PIC] Unknown RESIZE_FILTER {}, using mitchell",
+            config::RESIZE_FILTER
+        );
+        Filter::Mitchell
+    });
+
+    let anchor = Anchor::parse(config::CROP_ANCHOR).unwrap_or_else(|| {
+        warn!(
+            "[PIC] Unknown CROP_ANCHOR {}, using center",
+            config::CROP_ANCHOR
+        );
+        Anchor::CENTER
+    });
+    let mode = ScaleMode::parse(config::SCALE_MODE, anchor).unwrap_or_else(|| {
+        warn!(
+            "[PIC] Unknown SCALE_MODE {}, using contain",
+            config::SCALE_MODE
+        );
+        ScaleMode::Contain
+    });
+
+    let mat_color = palette_color(config::MAT_COLOR).unwrap_or_else(|| {
+        warn!("[PIC] Unknown MAT_COLOR {}, using white", config::MAT_COLOR);
+        HexColor::White
+    });
+    let mat = Mat::parse(config::MAT_STYLE, mat_color).unwrap_or_else(|| {
+        warn!("[PIC] Unknown MAT_STYLE {}, using solid", config::MAT_STYLE);
+        Mat::Solid(mat_color)
+    });
+    let border = palette_color(config::MAT_BORDER);
+
+    let white_balance =
+        Tone::parse_white_balance(config::TONE_WHITE_BALANCE).unwrap_or_else(|| {
+            warn!("[PIC] Could not read TONE_WHITE_BALANCE, leaving it alone");
+            [1.0; 3]
+        });
+
+    let space = ColorSpace::parse(config::PALETTE_SPACE).unwrap_or_else(|| {
+        warn!(
+            "[PIC] Unknown PALETTE_SPACE {}, using oklab",
+            config::PALETTE_SPACE
+        );
+        ColorSpace::OkLab
+    });
+    let palette = Palette::new(space, config::PANEL_PALETTE).unwrap_or_else(|| {
+        warn!("[PIC] Could not read PANEL_PALETTE, using the measured colors");
+        Palette::new(space, "").unwrap()
+    });
+
+    let method = Dither::parse(config::DITHER).unwrap_or_else(|| {
+        warn!(
+            "[PIC] Unknown DITHER {}, using floyd-steinberg",
+            config::DITHER
+        );
+        Dither::FloydSteinberg
+    });
+
+    info!("[PIC] image size: {:?}x{:?}", decoded.width, decoded.height);
+
+    // Straight into the display's buffer a few rows at a time, no full size copies on the way
+    let photo = render(
+        decoded,
+        &RenderOptions {
+            mode,
+            filter,
+            focus,
+            tone: Tone {
+                auto_levels: config::TONE_AUTO_LEVELS,
+                contrast: config::TONE_CONTRAST as f32 / 100.0,
+                gamma: config::TONE_GAMMA as f32 / 100.0,
+                saturation: config::TONE_SATURATION as f32 / 100.0,
+                vibrance: config::TONE_VIBRANCE as f32 / 100.0,
+                white_balance,
+                sharpen: config::TONE_SHARPEN as f32 / 100.0,
+            },
+            mat,
+            palette: &palette,
+            gamut_knee: config::GAMUT_MAP.then_some(config::GAMUT_KNEE as f32 / 100.0),
+            dither: method,
+            dither_options: DitherOptions {
+                serpentine: config::DITHER_SERPENTINE,
+                linear: config::DITHER_LINEAR,
+                error_limit: config::DITHER_ERROR_LIMIT as f32,
+                error_keep: config::DITHER_ERROR_KEEP as f32 / 100.0,
+            },
+        },
+        display.get_mut_buffer(),
+        size.width as usize,
+        size.height as usize,
+    );
+    info!(
+        "[PIC] Drawn at {:?},{:?} size {:?}x{:?}",
+        photo.x, photo.y, photo.width, photo.height
+    );
+
+    // Around the photo itself, not the mat
+    if let Some(color) = border {
+        Rectangle::new(
+            Point::new(photo.x as i32, photo.y as i32),
+            Size::new(photo.width as u32, photo.height as u32),
+        )
         .into_styled(
             PrimitiveStyleBuilder::new()
-                .fill_color(if charge_state.percent <= 10 {
-                    HexColor::Red
-                } else {
-                    HexColor::Black
-                })
+                .stroke_color(color)
+                .stroke_width(2)
                 .build(),
         )
-        .draw(display.as_mut())
+        .draw(display)
         .unwrap();
+    }
+}
+
+fn draw_message(display: &mut Display7in3e, message: &str) {
+    let size = display.size();
 
     Text::with_alignment(
-        format!("{:?}%", charge_state.percent).as_str(),
-        Point::new(50, 25),
-        MonoTextStyle::new(&FONT_10X20, HexColor::White),
-        Alignment::Left,
+        message,
+        Point::new((size.width / 2) as i32, (size.height / 2) as i32),
+        MonoTextStyle::new(&FONT_10X20, HexColor::Black),
+        Alignment::Center,
     )
-    .draw(display.as_mut())
+    .draw(display)
     .unwrap();
-
-    epd7in3e
-        .update_and_display_frame(&mut epd_spi_dev, display.buffer(), &mut delay)
-        .unwrap();
-
-    epd7in3e.sleep(&mut epd_spi_dev, &mut delay).unwrap();
-
-    let wakeup_pins: &mut [(
-        &mut dyn esp_hal::gpio::RtcPin,
-        esp_hal::rtc_cntl::sleep::WakeupLevel,
-    )] = &mut [(
-        &mut gpio_btn_reset,
-        esp_hal::rtc_cntl::sleep::WakeupLevel::Low,
-    )];
-    let pin_wake_source = esp_hal::rtc_cntl::sleep::RtcioWakeupSource::new(wakeup_pins);
-
-    let timer_wake_source =
-        esp_hal::rtc_cntl::sleep::TimerWakeupSource::new(core::time::Duration::from_hours(9));
-    let wake_sources: &[&dyn esp_hal::rtc_cntl::sleep::WakeSource] =
-        &[&timer_wake_source, &pin_wake_source];
-
-    info!("[ESP] Going to deep sleep :)");
-    rtc.sleep_deep(wake_sources);
 }
 
 #[embassy_executor::task]
@@ -303,6 +814,9 @@ async fn wifi_task(mut controller: WifiController<'static>) {
         match controller.connect_async().await {
             Ok(_) => {
                 info!("[NET] Connected");
+                if let Ok(rssi) = controller.rssi() {
+                    RSSI.store(rssi, Ordering::Relaxed);
+                }
                 controller.wait_for_disconnect_async().await.ok();
                 info!("[NET] Disconnected");
             }
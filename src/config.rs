@@ -0,0 +1,215 @@
+// Settings baked in at build time from `.env`, see `build.rs` and `.env.example`
+
+/// Which backend photos come from
+pub const PHOTO_SOURCE: &str = or_default(option_env!("PHOTO_SOURCE"), "synology");
+
+pub const SYN_BASE: &str = or_default(option_env!("SYN_BASE"), "");
+pub const SYN_USER: &str = or_default(option_env!("SYN_USER"), "");
+pub const SYN_PASS: &str = or_default(option_env!("SYN_PASS"), "");
+/// `photos` for Synology Photos, `photostation` for DSM 6 Photo Station, `auto` to ask the NAS
+pub const SYN_API: &str = or_default(option_env!("SYN_API"), "auto");
+/// Passphrase from the album's share link, or the `album_...` id for Photo Station
+pub const SYN_ALBUM: &str = or_default(option_env!("SYN_ALBUM"), "");
+/// Shared folder for the File Station source, e.g. `/photo/Frame`
+pub const SYN_FOLDER: &str = or_default(option_env!("SYN_FOLDER"), "");
+/// Also show photos from folders inside `SYN_FOLDER`
+pub const SYN_FOLDER_RECURSIVE: bool = parse_bool(option_env!("SYN_FOLDER_RECURSIVE"), false);
+
+pub const IMMICH_BASE: &str = or_default(option_env!("IMMICH_BASE"), "");
+/// API key from Immich's account settings
+pub const IMMICH_API_KEY: &str = or_default(option_env!("IMMICH_API_KEY"), "");
+/// Album id from the album's URL, leave empty for random photos from the whole library
+pub const IMMICH_ALBUM: &str = or_default(option_env!("IMMICH_ALBUM"), "");
+
+/// JPEG to download every wake, basic auth comes from `HTTP_BASIC_AUTH`
+pub const IMAGE_URL: &str = or_default(option_env!("IMAGE_URL"), "");
+/// Sent as `Authorization: Bearer ...`, e.g. a Home Assistant long lived access token
+pub const IMAGE_URL_TOKEN: Option<&str> = option_env!("IMAGE_URL_TOKEN");
+
+/// WebDAV folder with the photos, e.g. `https://cloud.example.com/remote.php/dav/files/me/Frame`
+pub const WEBDAV_URL: &str = or_default(option_env!("WEBDAV_URL"), "");
+pub const WEBDAV_USER: &str = or_default(option_env!("WEBDAV_USER"), "");
+pub const WEBDAV_PASS: &str = or_default(option_env!("WEBDAV_PASS"), "");
+
+/// S3 compatible server without the bucket, e.g. `https://minio.example.com:9000`
+pub const S3_ENDPOINT: &str = or_default(option_env!("S3_ENDPOINT"), "");
+pub const S3_BUCKET: &str = or_default(option_env!("S3_BUCKET"), "");
+/// Only keys starting with this, e.g. `frame/`
+pub const S3_PREFIX: &str = or_default(option_env!("S3_PREFIX"), "");
+pub const S3_REGION: &str = or_default(option_env!("S3_REGION"), "us-east-1");
+pub const S3_ACCESS_KEY: &str = or_default(option_env!("S3_ACCESS_KEY"), "");
+pub const S3_SECRET_KEY: &str = or_default(option_env!("S3_SECRET_KEY"), "");
+
+/// Folder on the SD card for `PHOTO_SOURCE=sdcard`, 8.3 names only
+pub const SD_FOLDER: &str = or_default(option_env!("SD_FOLDER"), "PHOTOS");
+/// Keep a copy of every photo fetched over the network on the SD card,
+/// and show one of those when the network is down
+pub const SD_CACHE: bool = parse_bool(option_env!("SD_CACHE"), false);
+/// A random cached photo makes room once there are this many
+pub const SD_CACHE_MAX: u32 = parse_u32(option_env!("SD_CACHE_MAX"), 200);
+
+/// MQTT broker for Home Assistant, leave empty to skip MQTT
+pub const MQTT_HOST: &str = or_default(option_env!("MQTT_HOST"), "");
+pub const MQTT_PORT: u32 = parse_u32(option_env!("MQTT_PORT"), 1883);
+pub const MQTT_USER: Option<&str> = option_env!("MQTT_USER");
+pub const MQTT_PASS: &str = or_default(option_env!("MQTT_PASS"), "");
+/// State goes to `<topic>/state`, commands are read from `<topic>/cmd/#`
+pub const MQTT_TOPIC: &str = or_default(option_env!("MQTT_TOPIC"), "photo_frame");
+
+/// Battery level at which the frame stops showing photos and asks for a charge
+pub const LOW_BATTERY_PERCENT: u32 = parse_u32(option_env!("LOW_BATTERY_PERCENT"), 5);
+const _: () = assert!(
+    LOW_BATTERY_PERCENT <= 100,
+    "LOW_BATTERY_PERCENT is 0 to 100"
+);
+/// `ntfy`, `gotify` or `webhook` to get told when the battery runs low, leave empty for nothing
+pub const NOTIFY_SERVICE: &str = or_default(option_env!("NOTIFY_SERVICE"), "");
+/// ntfy topic URL, Gotify server or webhook URL
+pub const NOTIFY_URL: &str = or_default(option_env!("NOTIFY_URL"), "");
+/// Gotify app token, or sent as `Authorization: Bearer ...` for ntfy and webhooks
+pub const NOTIFY_TOKEN: Option<&str> = option_env!("NOTIFY_TOKEN");
+/// Name used in notifications, handy with more than one frame
+pub const DEVICE_NAME: &str = or_default(option_env!("DEVICE_NAME"), "Photo frame");
+
+/// Where the time comes from when a source needs it
+pub const NTP_SERVER: &str = or_default(option_env!("NTP_SERVER"), "pool.ntp.org");
+
+/// Set when Synology Photos sits behind a reverse proxy under a sub path, e.g. `/photos`
+pub const SYN_PATH_PREFIX: &str = or_default(option_env!("SYN_PATH_PREFIX"), "");
+
+/// Extra headers sent with every request, `Name: value` pairs separated by `;`.
+/// e.g. `CF-Access-Client-Id: abc.access; CF-Access-Client-Secret: xyz`
+pub const HTTP_HEADERS: &str = or_default(option_env!("HTTP_HEADERS"), "");
+
+/// `user:password` for a proxy doing HTTP basic auth
+pub const HTTP_BASIC_AUTH: Option<&str> = option_env!("HTTP_BASIC_AUTH");
+
+/// `box`, `mitchell` or `lanczos3`. Box is the cheapest, lanczos3 the sharpest
+pub const RESIZE_FILTER: &str = or_default(option_env!("RESIZE_FILTER"), "mitchell");
+
+/// `contain` shows the whole photo with bars, `cover` fills the panel and crops, `smart` crops
+/// around faces or whatever stands out, `stretch` squashes
+pub const SCALE_MODE: &str = or_default(option_env!("SCALE_MODE"), "contain");
+/// Part of the photo `cover` keeps: `center`, `top`, `bottom-right` etc. or `x,y` in percent
+pub const CROP_ANCHOR: &str = or_default(option_env!("CROP_ANCHOR"), "center");
+
+/// What fills the bars around a `contain` photo: `solid` in `MAT_COLOR`, `dominant` for the
+/// photo's most common color or `blur` for a blurred copy of the photo
+pub const MAT_STYLE: &str = or_default(option_env!("MAT_STYLE"), "solid");
+/// black, white, yellow, red, blue or green
+pub const MAT_COLOR: &str = or_default(option_env!("MAT_COLOR"), "white");
+/// Color of a thin line around the photo, leave empty for none
+pub const MAT_BORDER: &str = or_default(option_env!("MAT_BORDER"), "");
+
+/// `oklab` or `cielab`, where photo colors are matched to the panel's inks
+pub const PALETTE_SPACE: &str = or_default(option_env!("PALETTE_SPACE"), "oklab");
+/// What the inks look like on this panel, `name=RRGGBB` pairs separated by `,`.
+/// e.g. `red=A01010,white=DDDDD8`, anything left out keeps the measured default
+pub const PANEL_PALETTE: &str = or_default(option_env!("PANEL_PALETTE"), "");
+
+/// Pull colors the inks can't show towards ones they can, keeping hue and lightness.
+/// Stops skin and skies from turning into red, blue and green speckle
+pub const GAMUT_MAP: bool = parse_bool(option_env!("GAMUT_MAP"), true);
+/// Percent of the inks' reach where colors start getting pulled in, lower is gentler on
+/// the strongest colors but dulls more of the photo
+pub const GAMUT_KNEE: u32 = parse_u32(option_env!("GAMUT_KNEE"), 70);
+
+/// `floyd-steinberg`, `atkinson`, `jarvis`, `stucki`, `sierra`, `sierra-lite`, `bayer` or `blue-noise`
+pub const DITHER: &str = or_default(option_env!("DITHER"), "floyd-steinberg");
+/// Every other row is dithered right to left, less streaking with the diffusion methods
+pub const DITHER_SERPENTINE: bool = parse_bool(option_env!("DITHER_SERPENTINE"), false);
+/// Spread the error in linear light, turn off for the old darker midtones
+pub const DITHER_LINEAR: bool = parse_bool(option_env!("DITHER_LINEAR"), true);
+/// Biggest error passed on per channel, 0-255. Lower means less speckle in flat areas, 0 is no limit
+pub const DITHER_ERROR_LIMIT: u32 = parse_u32(option_env!("DITHER_ERROR_LIMIT"), 0);
+/// Percent of the error passed on, a bit under 100 calms noise down
+pub const DITHER_ERROR_KEEP: u32 = parse_u32(option_env!("DITHER_ERROR_KEEP"), 100);
+
+/// Stretch the darkest and brightest parts of the photo to black and white
+pub const TONE_AUTO_LEVELS: bool = parse_bool(option_env!("TONE_AUTO_LEVELS"), false);
+/// S-curve strength in percent, 0 is off and 100 the strongest
+pub const TONE_CONTRAST: u32 = parse_u32(option_env!("TONE_CONTRAST"), 0);
+/// Gamma in percent, over 100 brightens midtones and under darkens them
+pub const TONE_GAMMA: u32 = parse_u32(option_env!("TONE_GAMMA"), 100);
+/// Saturation in percent, 100 leaves colors alone
+pub const TONE_SATURATION: u32 = parse_u32(option_env!("TONE_SATURATION"), 100);
+/// Extra saturation for dull colors only, in percent
+pub const TONE_VIBRANCE: u32 = parse_u32(option_env!("TONE_VIBRANCE"), 0);
+/// `r,g,b` multipliers in percent, e.g. `100,100,90` to warm things up
+pub const TONE_WHITE_BALANCE: &str = or_default(option_env!("TONE_WHITE_BALANCE"), "");
+/// Unsharp mask amount in percent, 0 is off
+pub const TONE_SHARPEN: u32 = parse_u32(option_env!("TONE_SHARPEN"), 0);
+
+/// How long the album listing cached in flash is used before listing the album again
+pub const ALBUM_CACHE_HOURS: u32 = parse_u32(option_env!("ALBUM_CACHE_HOURS"), 24);
+
+/// Changes whenever anything that decides which photos we show changes
+pub fn source_fingerprint() -> u32 {
+    crate::state::fingerprint(&[
+        PHOTO_SOURCE,
+        SYN_BASE,
+        SYN_PATH_PREFIX,
+        SYN_USER,
+        SYN_PASS,
+        SYN_API,
+        SYN_ALBUM,
+        SYN_FOLDER,
+        if SYN_FOLDER_RECURSIVE {
+            "recursive"
+        } else {
+            ""
+        },
+        IMMICH_BASE,
+        IMMICH_API_KEY,
+        IMMICH_ALBUM,
+        IMAGE_URL,
+        WEBDAV_URL,
+        WEBDAV_USER,
+        S3_ENDPOINT,
+        S3_BUCKET,
+        S3_PREFIX,
+        S3_ACCESS_KEY,
+        SD_FOLDER,
+    ])
+}
+
+const fn or_default(value: Option<&'static str>, default: &'static str) -> &'static str {
+    match value {
+        Some(v) => v,
+        None => default,
+    }
+}
+
+/// `true`/`1` or `false`/`0`
+const fn parse_bool(value: Option<&str>, default: bool) -> bool {
+    match value {
+        Some(value) => match value.as_bytes() {
+            b"true" | b"1" => true,
+            b"false" | b"0" | b"" => false,
+            _ => panic!("expected true or false"),
+        },
+        None => default,
+    }
+}
+
+/// `env!` values are strings, this turns them into numbers at compile time
+const fn parse_u32(value: Option<&str>, default: u32) -> u32 {
+    let Some(value) = value else {
+        return default;
+    };
+
+    let bytes = value.as_bytes();
+    if bytes.is_empty() {
+        return default;
+    }
+
+    let mut result: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        assert!(digit.is_ascii_digit(), "expected a whole number");
+        result = result * 10 + (digit - b'0') as u32;
+        i += 1;
+    }
+    result
+}
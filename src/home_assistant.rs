@@ -0,0 +1,244 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::info;
+use embassy_time::Duration;
+use serde_json::json;
+
+use crate::mqtt::{self, Client};
+
+extern crate alloc;
+
+// Home Assistant over MQTT. Every wake the frame reads the retained commands under
+// `<topic>/cmd/` before fetching and publishes its state plus discovery configs afterwards.
+// Commands are only seen on the next wake, the frame is asleep the rest of the time
+
+/// How long the broker can stay quiet before we assume all retained commands arrived
+const RETAINED_WAIT: Duration = Duration::from_secs(2);
+
+pub struct Broker<'a> {
+    pub stack: embassy_net::Stack<'static>,
+    pub host: &'a str,
+    pub port: u16,
+    pub credentials: Option<(&'a str, &'a str)>,
+    /// Base topic, e.g. `photo_frame/living_room`
+    pub topic: &'a str,
+}
+
+#[derive(Default)]
+pub struct Commands {
+    /// Show a new photo even while paused
+    pub next: bool,
+    /// Album to use instead of the configured one
+    pub album: Option<String>,
+    /// Keep the current photo up and only report state
+    pub paused: bool,
+}
+
+pub struct Status<'a> {
+    pub battery_percent: i8,
+    pub battery_mv: u16,
+    pub rssi: Option<i32>,
+    pub photo: Option<&'a str>,
+    pub error: Option<&'a str>,
+}
+
+impl Broker<'_> {
+    async fn connect<'b>(
+        &self,
+        rx: &'b mut [u8],
+        tx: &'b mut [u8],
+    ) -> Result<Client<'b>, mqtt::Error> {
+        Client::connect(
+            self.stack,
+            rx,
+            tx,
+            self.host,
+            self.port,
+            &self.node_id(),
+            self.credentials,
+        )
+        .await
+    }
+
+    /// Unique id for discovery, the topic with the `/` swapped out
+    fn node_id(&self) -> String {
+        self.topic.replace('/', "_")
+    }
+
+    pub async fn read_commands(&self) -> Result<Commands, mqtt::Error> {
+        let mut rx = [0u8; 1024];
+        let mut tx = [0u8; 512];
+        let mut client = self.connect(&mut rx, &mut tx).await?;
+
+        let prefix = format!("{}/cmd/", self.topic);
+        let filter = format!("{}+", prefix);
+        client.subscribe(&[filter.as_str()]).await?;
+
+        let mut commands = Commands::default();
+        while let Some(message) = client.next_message(RETAINED_WAIT).await? {
+            let Some(command) = message.topic.strip_prefix(prefix.as_str()) else {
+                continue;
+            };
+            let payload = core::str::from_utf8(&message.payload).unwrap_or("").trim();
+            info!("[HA] Command {}: {}", command, payload);
+
+            match command {
+                "next" => commands.next = !payload.is_empty(),
+                "album" if !payload.is_empty() => commands.album = Some(payload.to_string()),
+                "pause" => commands.paused = payload.eq_ignore_ascii_case("ON"),
+                _ => {}
+            }
+        }
+
+        client.disconnect().await;
+        Ok(commands)
+    }
+
+    /// Next photo is a one off, clears the retained press so it doesn't fire every wake.
+    /// Only once the new photo is up, a press that didn't get one stays for the next wake
+    pub async fn clear_next(&self) -> Result<(), mqtt::Error> {
+        let mut rx = [0u8; 256];
+        let mut tx = [0u8; 256];
+        let mut client = self.connect(&mut rx, &mut tx).await?;
+
+        client
+            .publish(&format!("{}/cmd/next", self.topic), &[], true)
+            .await?;
+
+        client.disconnect().await;
+        Ok(())
+    }
+
+    pub async fn publish(
+        &self,
+        status: &Status<'_>,
+        commands: &Commands,
+    ) -> Result<(), mqtt::Error> {
+        let mut rx = [0u8; 256];
+        let mut tx = [0u8; 1024];
+        let mut client = self.connect(&mut rx, &mut tx).await?;
+
+        for (topic, config) in self.discovery() {
+            client.publish(&topic, config.as_bytes(), true).await?;
+        }
+
+        let state = json!({
+            "battery": status.battery_percent,
+            "voltage": status.battery_mv as f32 / 1000.0,
+            "rssi": status.rssi,
+            "photo": status.photo,
+            "error": status.error,
+        });
+        client
+            .publish(
+                &format!("{}/state", self.topic),
+                state.to_string().as_bytes(),
+                true,
+            )
+            .await?;
+        client
+            .publish(
+                &format!("{}/pause", self.topic),
+                if commands.paused { "ON" } else { "OFF" }.as_bytes(),
+                true,
+            )
+            .await?;
+
+        client.disconnect().await;
+        info!("[HA] Published state");
+        Ok(())
+    }
+
+    /// Topic and config for every entity, sent each wake so HA picks up changes after a reflash
+    fn discovery(&self) -> Vec<(String, String)> {
+        let node = self.node_id();
+        let device = json!({
+            "identifiers": [node],
+            "name": "Photo Frame",
+            "manufacturer": "Seeed Studio",
+            "model": "reTerminal E1002",
+        });
+        let state_topic = format!("{}/state", self.topic);
+
+        let sensor = |key: &str, name: &str, extra: serde_json::Value| {
+            let mut config = json!({
+                "name": name,
+                "unique_id": format!("{}_{}", node, key),
+                "state_topic": state_topic,
+                "value_template": format!("{{{{ value_json.{} }}}}", key),
+                "device": device,
+            });
+            if let (Some(config), Some(extra)) = (config.as_object_mut(), extra.as_object()) {
+                config.extend(extra.clone());
+            }
+            (
+                format!("homeassistant/sensor/{}/{}/config", node, key),
+                config.to_string(),
+            )
+        };
+
+        let command = |component: &str, key: &str, name: &str, extra: serde_json::Value| {
+            let mut config = json!({
+                "name": name,
+                "unique_id": format!("{}_{}", node, key),
+                "command_topic": format!("{}/cmd/{}", self.topic, key),
+                // Frame is asleep when you press it, the broker has to hold on to it
+                "retain": true,
+                "device": device,
+            });
+            if let (Some(config), Some(extra)) = (config.as_object_mut(), extra.as_object()) {
+                config.extend(extra.clone());
+            }
+            (
+                format!("homeassistant/{}/{}/{}/config", component, node, key),
+                config.to_string(),
+            )
+        };
+
+        alloc::vec![
+            sensor(
+                "battery",
+                "Battery",
+                json!({ "device_class": "battery", "unit_of_measurement": "%" }),
+            ),
+            sensor(
+                "voltage",
+                "Battery voltage",
+                json!({
+                    "device_class": "voltage",
+                    "unit_of_measurement": "V",
+                    "entity_category": "diagnostic",
+                }),
+            ),
+            sensor(
+                "rssi",
+                "WiFi signal",
+                json!({
+                    "device_class": "signal_strength",
+                    "unit_of_measurement": "dBm",
+                    "entity_category": "diagnostic",
+                }),
+            ),
+            sensor("photo", "Photo", json!({})),
+            sensor(
+                "error",
+                "Last error",
+                json!({ "entity_category": "diagnostic" })
+            ),
+            command(
+                "button",
+                "next",
+                "Next photo",
+                json!({ "payload_press": "PRESS" })
+            ),
+            command("text", "album", "Album", json!({})),
+            command(
+                "switch",
+                "pause",
+                "Pause",
+                json!({ "state_topic": format!("{}/pause", self.topic) }),
+            ),
+        ]
+    }
+}
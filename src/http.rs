@@ -0,0 +1,347 @@
+use alloc::boxed::Box;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{error, info};
+use embassy_net::dns::DnsSocket;
+use embassy_net::tcp::client::{TcpClient, TcpClientState};
+use embedded_io_async::{BufRead, Read, Write};
+use reqwless::client::TlsConfig;
+use reqwless::headers::ContentType;
+use reqwless::request::{Method, RequestBuilder};
+use reqwless::response::Response;
+
+use crate::retry::Retryable;
+
+extern crate alloc;
+
+pub type HttpClient =
+    reqwless::client::HttpClient<'static, TcpClient<'static, 1, 2048, 2048>, DnsSocket<'static>>;
+
+/// Biggest body we are willing to hold in memory. Thumbnails are a few hundred KB at most
+pub const MAX_BODY_LEN: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, defmt::Format)]
+pub enum Error {
+    /// Connection, TLS or protocol failure inside reqwless
+    Request(reqwless::Error),
+    /// Server answered but not with a 2xx
+    Status(u16),
+    /// Body is over `MAX_BODY_LEN`
+    TooLarge,
+    /// Ran out of time for this wake
+    Timeout,
+    /// Configured URL doesn't parse
+    InvalidUrl,
+}
+
+impl Retryable for Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::Request(_) => true,
+            Error::Status(code) => *code >= 500 || *code == 408 || *code == 429,
+            Error::TooLarge => false,
+            Error::Timeout => false,
+            Error::InvalidUrl => false,
+        }
+    }
+}
+
+impl From<reqwless::Error> for Error {
+    fn from(e: reqwless::Error) -> Self {
+        Error::Request(e)
+    }
+}
+
+impl From<embassy_time::TimeoutError> for Error {
+    fn from(_: embassy_time::TimeoutError) -> Self {
+        Error::Timeout
+    }
+}
+
+/// Where a server lives plus the headers every request to it needs.
+/// Covers servers behind a reverse proxy with a sub path, Cloudflare Access or basic auth
+#[derive(Clone)]
+pub struct Endpoint {
+    /// Scheme, host, port and path prefix without a trailing `/`
+    base: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Endpoint {
+    pub fn new(base: &str, path_prefix: &str) -> Self {
+        let prefix = path_prefix.trim_matches('/');
+        let base = base.trim_end_matches('/');
+
+        Self {
+            base: if prefix.is_empty() {
+                base.to_string()
+            } else {
+                format!("{}/{}", base, prefix)
+            },
+            headers: Vec::new(),
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Adds `Name: value` pairs separated by `;`, anything without a `:` is skipped
+    pub fn with_headers(mut self, raw: &str) -> Self {
+        for pair in raw.split(';') {
+            if let Some((name, value)) = pair.split_once(':') {
+                let (name, value) = (name.trim(), value.trim());
+                if !name.is_empty() {
+                    self = self.with_header(name, value);
+                }
+            }
+        }
+        self
+    }
+
+    /// `credentials` is `user:password`
+    pub fn with_basic_auth(self, credentials: &str) -> Self {
+        let value = format!("Basic {}", base64(credentials.as_bytes()));
+        self.with_header("Authorization", &value)
+    }
+
+    pub fn with_bearer_token(self, token: &str) -> Self {
+        let value = format!("Bearer {}", token);
+        self.with_header("Authorization", &value)
+    }
+
+    pub fn base(&self) -> &str {
+        &self.base
+    }
+
+    /// `path` starts with `/`
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{}", self.base, path)
+    }
+
+    pub fn headers(&self) -> Vec<(&str, &str)> {
+        self.headers
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+}
+
+fn base64(input: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3f] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// The device deep sleeps (and resets) after every wake so the client lives until then.
+/// Leaking the sockets and TLS buffers saves threading their lifetimes through everything
+pub fn new_client(stack: embassy_net::Stack<'static>, seed: u64) -> HttpClient {
+    let dns = Box::leak(Box::new(DnsSocket::new(stack)));
+    let tcp_state = Box::leak(Box::new(TcpClientState::<1, 2048, 2048>::new()));
+    let tcp = Box::leak(Box::new(TcpClient::new(stack, tcp_state)));
+
+    let write_buffer = Box::leak(alloc::vec![0u8; 2048].into_boxed_slice());
+    let read_buffer = Box::leak(alloc::vec![0u8; 16640].into_boxed_slice());
+    let config = TlsConfig::new(
+        seed,
+        read_buffer,
+        write_buffer,
+        reqwless::client::TlsVerify::None,
+    );
+
+    info!("[HTTP] Ready");
+
+    reqwless::client::HttpClient::new_with_tls(tcp, dns, config)
+}
+
+pub async fn get(
+    client: &mut HttpClient,
+    url: &str,
+    headers: &[(&str, &str)],
+) -> Result<Vec<u8>, Error> {
+    info!("[HTTP] GET {}", url);
+    info!("[HTTP] -> {}", esp_alloc::HEAP.stats());
+
+    let mut request = client.request(Method::GET, url).await?.headers(headers);
+
+    let mut http_rx_buf = alloc::vec![0u8; 4096];
+    let response = request.send(&mut http_rx_buf).await?;
+    read_response(response).await
+}
+
+pub async fn post(
+    client: &mut HttpClient,
+    url: &str,
+    headers: &[(&str, &str)],
+    content_type: ContentType,
+    body: &[u8],
+) -> Result<Vec<u8>, Error> {
+    info!("[HTTP] POST {}", url);
+
+    let mut request = client
+        .request(Method::POST, url)
+        .await?
+        .headers(headers)
+        .content_type(content_type)
+        .body(body);
+
+    let mut http_rx_buf = alloc::vec![0u8; 4096];
+    let response = request.send(&mut http_rx_buf).await?;
+    read_response(response).await
+}
+
+/// WebDAV `PROPFIND`
+pub async fn propfind(
+    client: &mut HttpClient,
+    url: &str,
+    headers: &[(&str, &str)],
+    depth: u8,
+    body: &str,
+) -> Result<Vec<u8>, Error> {
+    let depth = format!("{}", depth);
+    let mut headers = headers.to_vec();
+    headers.push(("Depth", &depth));
+    headers.push(("Content-Type", "application/xml"));
+
+    request_raw(client, "PROPFIND", url, &headers, body.as_bytes()).await
+}
+
+/// `Host` exactly as `request_raw` sends it, the port is only there when it isn't the default
+pub fn host(url: &url::Url) -> Option<String> {
+    let host = url.host_str()?;
+    Some(match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    })
+}
+
+/// Status and body of a response, whatever the status was
+pub struct Reply {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl Reply {
+    /// Body if the status is 2xx
+    pub fn ok(self) -> Result<Vec<u8>, Error> {
+        if !(200..300).contains(&self.status) {
+            error!(
+                "[HTTP] {} {:?}",
+                self.status,
+                core::str::from_utf8(&self.body).unwrap_or("<binary>")
+            );
+            return Err(Error::Status(self.status));
+        }
+
+        Ok(self.body)
+    }
+}
+
+/// Request written by hand. For methods reqwless doesn't know and for signed requests
+/// where we need to control every header that goes out
+pub async fn request_raw(
+    client: &mut HttpClient,
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Vec<u8>, Error> {
+    send_raw(client, method, url, headers, body).await?.ok()
+}
+
+/// Like `request_raw` but hands back error responses too, for servers that explain
+/// themselves in the body
+pub async fn send_raw(
+    client: &mut HttpClient,
+    method: &str,
+    url: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<Reply, Error> {
+    info!("[HTTP] {} {}", method, url);
+
+    let parsed = url::Url::parse(url).map_err(|_| Error::InvalidUrl)?;
+    let host = host(&parsed).ok_or(Error::InvalidUrl)?;
+    let path = match parsed.query() {
+        Some(query) => format!("{}?{}", parsed.path(), query),
+        None => parsed.path().to_string(),
+    };
+
+    let mut head = format!("{} {} HTTP/1.1\r\nHost: {}\r\n", method, path, host);
+    if !body.is_empty() {
+        head.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    let mut resource = client.resource(url).await?;
+    resource.conn.write_all(head.as_bytes()).await?;
+    resource.conn.write_all(body).await?;
+    resource.conn.flush().await?;
+
+    // Method only matters to reqwless for HEAD, which has no body
+    let mut http_rx_buf = alloc::vec![0u8; 4096];
+    let response = Response::read(&mut resource.conn, Method::GET, &mut http_rx_buf).await?;
+    read_reply(response).await
+}
+
+/// Whole body if the status is 2xx
+pub async fn read_response<C: Read>(response: Response<'_, '_, C>) -> Result<Vec<u8>, Error> {
+    read_reply(response).await?.ok()
+}
+
+/// Whole body, whatever the status
+pub async fn read_reply<C: Read>(response: Response<'_, '_, C>) -> Result<Reply, Error> {
+    let status = response.status.0;
+
+    if response
+        .content_length
+        .is_some_and(|len| len > MAX_BODY_LEN)
+    {
+        return Err(Error::TooLarge);
+    }
+
+    let body = read_body(response.body().reader()).await?;
+    Ok(Reply { status, body })
+}
+
+pub async fn read_body(mut body: impl BufRead<Error = reqwless::Error>) -> Result<Vec<u8>, Error> {
+    let mut data = Vec::new();
+    loop {
+        let chunk = body.fill_buf().await?;
+        if chunk.is_empty() {
+            break;
+        }
+
+        if data.len() + chunk.len() > MAX_BODY_LEN {
+            return Err(Error::TooLarge);
+        }
+
+        data.extend_from_slice(chunk);
+        let len = chunk.len();
+        body.consume(len);
+    }
+
+    Ok(data)
+}
@@ -0,0 +1,49 @@
+use alloc::string::ToString;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::http::{self, Endpoint, HttpClient};
+use crate::source::{Error, PhotoEntry, PhotoSource};
+
+extern crate alloc;
+
+/// A single JPEG at a fixed URL, e.g. a Home Assistant camera snapshot or a `frame.jpg`
+/// some cron job keeps rewriting on a web share
+pub struct ImageUrlSource {
+    client: HttpClient,
+    /// Base is the whole image URL
+    endpoint: Endpoint,
+}
+
+impl ImageUrlSource {
+    pub fn new(client: HttpClient, endpoint: Endpoint) -> Self {
+        Self { client, endpoint }
+    }
+}
+
+impl PhotoSource for ImageUrlSource {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        Ok(vec![PhotoEntry {
+            id: "url".to_string(),
+            key: None,
+            name: Some(self.endpoint.base().to_string()),
+            taken_at: None,
+            focus: None,
+        }])
+    }
+
+    async fn fetch(&mut self, _entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        http::get(
+            &mut self.client,
+            self.endpoint.base(),
+            &self.endpoint.headers(),
+        )
+        .await
+        .map_err(|e| Error::from_auth(e, "IMAGE URL REJECTED THE CREDENTIALS"))
+    }
+
+    /// Always `url`, whatever the URL serves now can differ from last time
+    fn stable_ids(&self) -> bool {
+        false
+    }
+}
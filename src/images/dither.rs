@@ -0,0 +1,318 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{Palette, srgb_to_linear};
+
+extern crate alloc;
+
+// Works a row at a time. Error diffusion only keeps the rows of error its kernel reaches,
+// two for Floyd-Steinberg and three for the wider ones. Ordered methods don't need any.
+// Diffusing in sRGB values spreads too little light around, midtones come out dark and hues
+// drift. In linear light the dots average out to what the eye sees from a step back
+
+/// How far ordered dithering nudges a pixel either way, in 0-255 units. The inks are far
+/// apart so this is a lot bigger than it would be for a grayscale panel
+const ORDERED_SPREAD: f32 = 96.0;
+
+/// Kernels reach at most two pixels sideways, padding saves the bounds checks
+const PAD: usize = 2;
+
+/// `(dx, dy, weight)`, weights are divided by `divisor`
+struct Kernel {
+    divisor: f32,
+    taps: &'static [(i8, u8, f32)],
+}
+
+impl Kernel {
+    /// Rows of error it writes to, the current one included
+    fn rows(&self) -> usize {
+        self.taps
+            .iter()
+            .map(|&(_, dy, _)| dy as usize + 1)
+            .max()
+            .unwrap_or(1)
+    }
+}
+
+const FLOYD_STEINBERG: Kernel = Kernel {
+    divisor: 16.0,
+    taps: &[(1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0)],
+};
+
+/// Only passes on 6/8 of the error, keeps highlights and shadows clean
+const ATKINSON: Kernel = Kernel {
+    divisor: 8.0,
+    taps: &[
+        (1, 0, 1.0),
+        (2, 0, 1.0),
+        (-1, 1, 1.0),
+        (0, 1, 1.0),
+        (1, 1, 1.0),
+        (0, 2, 1.0),
+    ],
+};
+
+const JARVIS_JUDICE_NINKE: Kernel = Kernel {
+    divisor: 48.0,
+    taps: &[
+        (1, 0, 7.0),
+        (2, 0, 5.0),
+        (-2, 1, 3.0),
+        (-1, 1, 5.0),
+        (0, 1, 7.0),
+        (1, 1, 5.0),
+        (2, 1, 3.0),
+        (-2, 2, 1.0),
+        (-1, 2, 3.0),
+        (0, 2, 5.0),
+        (1, 2, 3.0),
+        (2, 2, 1.0),
+    ],
+};
+
+const STUCKI: Kernel = Kernel {
+    divisor: 42.0,
+    taps: &[
+        (1, 0, 8.0),
+        (2, 0, 4.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 8.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-2, 2, 1.0),
+        (-1, 2, 2.0),
+        (0, 2, 4.0),
+        (1, 2, 2.0),
+        (2, 2, 1.0),
+    ],
+};
+
+const SIERRA: Kernel = Kernel {
+    divisor: 32.0,
+    taps: &[
+        (1, 0, 5.0),
+        (2, 0, 3.0),
+        (-2, 1, 2.0),
+        (-1, 1, 4.0),
+        (0, 1, 5.0),
+        (1, 1, 4.0),
+        (2, 1, 2.0),
+        (-1, 2, 2.0),
+        (0, 2, 3.0),
+        (1, 2, 2.0),
+    ],
+};
+
+const SIERRA_LITE: Kernel = Kernel {
+    divisor: 4.0,
+    taps: &[(1, 0, 2.0), (-1, 1, 1.0), (0, 1, 1.0)],
+};
+
+/// Standard 8x8 Bayer matrix
+const BAYER_8: [[u8; 8]; 8] = [
+    [0, 32, 8, 40, 2, 34, 10, 42],
+    [48, 16, 56, 24, 50, 18, 58, 26],
+    [12, 44, 4, 36, 14, 46, 6, 38],
+    [60, 28, 52, 20, 62, 30, 54, 22],
+    [3, 35, 11, 43, 1, 33, 9, 41],
+    [51, 19, 59, 27, 49, 17, 57, 25],
+    [15, 47, 7, 39, 13, 45, 5, 37],
+    [63, 31, 55, 23, 61, 29, 53, 21],
+];
+
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct DitherOptions {
+    /// Every other row right to left so diffusion doesn't smear everything the same way
+    pub serpentine: bool,
+    /// Diffuse error in linear light instead of sRGB values
+    pub linear: bool,
+    /// Biggest error passed on per channel in 0-255 units, 0 for no limit.
+    /// Stops a single far off pixel from throwing speckles across a flat area
+    pub error_limit: f32,
+    /// Share of the error passed on, 1.0 is textbook diffusion
+    pub error_keep: f32,
+}
+
+impl Default for DitherOptions {
+    fn default() -> Self {
+        Self {
+            serpentine: false,
+            linear: true,
+            error_limit: 0.0,
+            error_keep: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub enum Dither {
+    FloydSteinberg,
+    Atkinson,
+    JarvisJudiceNinke,
+    Stucki,
+    Sierra,
+    SierraLite,
+    /// Regular cross hatch pattern, good for graphics and text
+    Bayer,
+    /// Threshold from the R2 sequence. Not a real blue noise texture but close in look,
+    /// no visible pattern and no error creeping across flat areas
+    BlueNoise,
+}
+
+impl Dither {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "floyd-steinberg" => Some(Self::FloydSteinberg),
+            "atkinson" => Some(Self::Atkinson),
+            "jarvis" => Some(Self::JarvisJudiceNinke),
+            "stucki" => Some(Self::Stucki),
+            "sierra" => Some(Self::Sierra),
+            "sierra-lite" => Some(Self::SierraLite),
+            "bayer" => Some(Self::Bayer),
+            "blue-noise" => Some(Self::BlueNoise),
+            _ => None,
+        }
+    }
+
+    fn kernel(self) -> Option<&'static Kernel> {
+        match self {
+            Self::FloydSteinberg => Some(&FLOYD_STEINBERG),
+            Self::Atkinson => Some(&ATKINSON),
+            Self::JarvisJudiceNinke => Some(&JARVIS_JUDICE_NINKE),
+            Self::Stucki => Some(&STUCKI),
+            Self::Sierra => Some(&SIERRA),
+            Self::SierraLite => Some(&SIERRA_LITE),
+            Self::Bayer | Self::BlueNoise => None,
+        }
+    }
+}
+
+/// Dithers an image one row per call, top to bottom
+pub struct Ditherer<'a> {
+    width: usize,
+    palette: &'a Palette,
+    method: Dither,
+    options: DitherOptions,
+    /// Next row's number
+    y: usize,
+    /// Input is only ever 0-255, a table saves a powf per channel per pixel
+    to_linear: [f32; 256],
+    /// The current row's error and the ones ahead the kernel reaches, padded on both sides
+    errors: Vec<[f32; 3]>,
+}
+
+impl<'a> Ditherer<'a> {
+    /// Ordered methods ignore `options`
+    pub fn new(
+        width: usize,
+        palette: &'a Palette,
+        method: Dither,
+        options: &DitherOptions,
+    ) -> Self {
+        let mut to_linear = [0.0f32; 256];
+        for (i, v) in to_linear.iter_mut().enumerate() {
+            *v = if options.linear {
+                srgb_to_linear(i as f32) * 255.0
+            } else {
+                i as f32
+            };
+        }
+
+        let rows = method.kernel().map_or(0, Kernel::rows);
+
+        Self {
+            width,
+            palette,
+            method,
+            options: *options,
+            y: 0,
+            to_linear,
+            errors: vec![[0.0; 3]; (width + PAD * 2) * rows],
+        }
+    }
+
+    /// Packed RGB888 row in, one `HexColor` nibble per pixel into `out`
+    pub fn row(&mut self, src: &[u8], out: &mut [u8]) {
+        assert_eq!(src.len(), self.width * 3);
+
+        match self.method.kernel() {
+            Some(kernel) => self.diffuse(src, out, kernel),
+            None => self.ordered(src, out),
+        }
+        self.y += 1;
+    }
+
+    fn diffuse(&mut self, src: &[u8], out: &mut [u8], kernel: &Kernel) {
+        let (width, options) = (self.width, &self.options);
+        let limit = if options.error_limit > 0.0 {
+            options.error_limit
+        } else {
+            f32::MAX
+        };
+        let stride = width + PAD * 2;
+        let reverse = options.serpentine && self.y % 2 == 1;
+
+        for i in 0..width {
+            let x = if reverse { width - 1 - i } else { i };
+            let p = x * 3;
+            let e = self.errors[PAD + x];
+
+            let old = [
+                self.to_linear[src[p] as usize] + e[0],
+                self.to_linear[src[p + 1] as usize] + e[1],
+                self.to_linear[src[p + 2] as usize] + e[2],
+            ];
+            let (color, quant) = if options.linear {
+                self.palette.nearest_linear(old)
+            } else {
+                self.palette.nearest(old)
+            };
+            out[x] = color.get_nibble();
+
+            let err =
+                [0, 1, 2].map(|c| ((old[c] - quant[c]) * options.error_keep).clamp(-limit, limit));
+
+            for &(dx, dy, weight) in kernel.taps {
+                let dx = if reverse { -dx } else { dx };
+                let column = (PAD + x) as isize + dx as isize;
+                // Past the edge is the padding, written and never read
+                let i = dy as usize * stride + column as usize;
+                let weight = weight / kernel.divisor;
+                for c in 0..3 {
+                    self.errors[i][c] += err[c] * weight;
+                }
+            }
+        }
+
+        // Next row's error moves up, the last row starts clean
+        let last = self.errors.len() - stride;
+        self.errors.copy_within(stride.., 0);
+        self.errors[last..].fill([0.0; 3]);
+    }
+
+    fn ordered(&self, src: &[u8], out: &mut [u8]) {
+        let y = self.y;
+
+        for x in 0..self.width {
+            // -0.5..0.5
+            let threshold = match self.method {
+                Dither::Bayer => (BAYER_8[y % 8][x % 8] as f32 + 0.5) / 64.0 - 0.5,
+                _ => {
+                    // R2 sequence, the plastic constant's inverse powers
+                    let t = x as f32 * 0.754_877_7 + y as f32 * 0.569_840_3;
+                    t - libm::floorf(t) - 0.5
+                }
+            };
+
+            let p = x * 3;
+            let nudge = threshold * ORDERED_SPREAD;
+            let (color, _) = self.palette.nearest([
+                src[p] as f32 + nudge,
+                src[p + 1] as f32 + nudge,
+                src[p + 2] as f32 + nudge,
+            ]);
+            out[x] = color.get_nibble();
+        }
+    }
+}
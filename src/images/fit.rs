@@ -0,0 +1,164 @@
+use super::{DecodedImage, Focus, Region, smart_anchor};
+
+// How a photo is fitted to the panel when the aspect ratios don't match
+
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub enum ScaleMode {
+    /// Whole photo visible, bars on two sides
+    Contain,
+    /// Fills the panel, whatever sticks out past the anchor is cut off
+    Cover(Anchor),
+    /// Like cover but the crop follows faces or whatever stands out in the photo
+    Smart,
+    /// Fills the panel and squashes the photo to fit
+    Stretch,
+}
+
+impl ScaleMode {
+    /// `contain`, `cover`, `smart` or `stretch`, cover is cropped around `anchor`
+    pub fn parse(name: &str, anchor: Anchor) -> Option<Self> {
+        match name {
+            "contain" => Some(Self::Contain),
+            "cover" => Some(Self::Cover(anchor)),
+            "smart" => Some(Self::Smart),
+            "stretch" => Some(Self::Stretch),
+            _ => None,
+        }
+    }
+}
+
+/// Which part of a cropped photo is kept, 0.0 keeps the left/top edge and 1.0 the right/bottom
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct Anchor {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Anchor {
+    pub const CENTER: Self = Self { x: 0.5, y: 0.5 };
+
+    /// `center`, `top`, `bottom-left` and so on, or `x,y` in percent like `50,30`
+    pub fn parse(value: &str) -> Option<Self> {
+        if let Some((x, y)) = value.split_once(',') {
+            let x: f32 = x.trim().parse().ok()?;
+            let y: f32 = y.trim().parse().ok()?;
+            return Some(Self {
+                x: (x / 100.0).clamp(0.0, 1.0),
+                y: (y / 100.0).clamp(0.0, 1.0),
+            });
+        }
+
+        let mut anchor = Self::CENTER;
+        for part in value.split('-') {
+            match part {
+                "center" => {}
+                "top" => anchor.y = 0.0,
+                "bottom" => anchor.y = 1.0,
+                "left" => anchor.x = 0.0,
+                "right" => anchor.x = 1.0,
+                _ => return None,
+            }
+        }
+        Some(anchor)
+    }
+}
+
+/// What part of a photo ends up on the panel and how big
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct Placement {
+    /// Part of the photo that's kept
+    pub source: Region,
+    /// Size it's scaled to. Contain can come back smaller than the panel, the others always fill it
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Where `image` goes on a `target_width` x `target_height` panel. `focus` is only used by `Smart`
+pub fn placement(
+    image: &DecodedImage,
+    target_width: usize,
+    target_height: usize,
+    mode: ScaleMode,
+    focus: Option<Focus>,
+) -> Placement {
+    let (width, height) = (image.width, image.height);
+    let whole = Region::whole(width, height);
+
+    let anchor = match mode {
+        ScaleMode::Contain => {
+            let (width, height) = contain_size(width, height, target_width, target_height);
+            return Placement {
+                source: whole,
+                width,
+                height,
+            };
+        }
+        ScaleMode::Stretch => {
+            return Placement {
+                source: whole,
+                width: target_width,
+                height: target_height,
+            };
+        }
+        ScaleMode::Cover(anchor) => anchor,
+        ScaleMode::Smart => smart_anchor(image, focus, target_width, target_height),
+    };
+
+    // Crop first so the resampler only touches pixels that end up on screen
+    let (crop_width, crop_height) = cover_crop(width, height, target_width, target_height);
+    Placement {
+        source: Region {
+            x: ((width - crop_width) as f32 * anchor.x) as usize,
+            y: ((height - crop_height) as f32 * anchor.y) as usize,
+            width: crop_width,
+            height: crop_height,
+        },
+        width: target_width,
+        height: target_height,
+    }
+}
+
+/// Largest size with the photo's aspect ratio that fits inside the target
+pub fn contain_size(
+    width: usize,
+    height: usize,
+    target_width: usize,
+    target_height: usize,
+) -> (usize, usize) {
+    let src_aspect = width as f32 / height as f32;
+    let target_aspect = target_width as f32 / target_height as f32;
+
+    let (new_width, new_height) = if src_aspect > target_aspect {
+        // Fit by width
+        (target_width, (target_width as f32 / src_aspect) as usize)
+    } else {
+        // Fit by height
+        ((target_height as f32 * src_aspect) as usize, target_height)
+    };
+
+    (new_width.max(1), new_height.max(1))
+}
+
+/// Part of the photo with the target's aspect ratio, as large as the photo allows
+pub fn cover_crop(
+    width: usize,
+    height: usize,
+    target_width: usize,
+    target_height: usize,
+) -> (usize, usize) {
+    let target_aspect = target_width as f32 / target_height as f32;
+
+    if width as f32 / height as f32 > target_aspect {
+        // Too wide, cut the sides
+        (
+            ((height as f32 * target_aspect) as usize).clamp(1, width),
+            height,
+        )
+    } else {
+        // Too tall, cut top and bottom
+        (
+            width,
+            ((width as f32 / target_aspect) as usize).clamp(1, height),
+        )
+    }
+}
@@ -0,0 +1,228 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use epd_waveshare::prelude::HexColor;
+
+use super::{Palette, linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear};
+
+extern crate alloc;
+
+// Six inks cover a tiny slice of sRGB. Anything outside it can only be dithered as a mix of
+// far away inks, which is the red/blue/green speckle on skin and skies. This pulls colors
+// inside the inks' hull in OKLab first, keeping hue and relative lightness and only giving
+// up chroma. Colors well inside the hull aren't touched
+
+/// Lightness steps in the chroma table
+const LIGHTNESS_STEPS: usize = 32;
+/// Hue steps in the chroma table
+const HUE_STEPS: usize = 64;
+/// How far from the inks' black and white lightness gets eased in instead of clipped
+const LIGHTNESS_ROLLOFF: f32 = 0.1;
+/// Entries in the linear to sRGB table, fine enough that the steps don't show after dithering
+const SRGB_STEPS: usize = 4096;
+
+/// Most chroma the inks can reach around the gray axis, by lightness and hue
+struct Gamut {
+    black: [f32; 3],
+    white: [f32; 3],
+    max_chroma: Vec<f32>,
+}
+
+impl Gamut {
+    fn new(palette: &Palette) -> Self {
+        let inks: Vec<([f32; 3], HexColor)> = palette
+            .inks()
+            .map(|(color, linear)| (linear_to_oklab(linear), color))
+            .collect();
+        let find = |wanted: HexColor| {
+            inks.iter()
+                .find(|(_, color)| *color == wanted)
+                .map(|(lab, _)| *lab)
+                .unwrap_or_default()
+        };
+        let (black, white) = (find(HexColor::Black), find(HexColor::White));
+        let points: Vec<[f32; 3]> = inks.iter().map(|(lab, _)| *lab).collect();
+
+        let mut max_chroma = vec![0.0; LIGHTNESS_STEPS * HUE_STEPS];
+        for l in 0..LIGHTNESS_STEPS {
+            let origin = axis(black, white, l as f32 / (LIGHTNESS_STEPS - 1) as f32);
+            for h in 0..HUE_STEPS {
+                let angle = h as f32 / HUE_STEPS as f32 * core::f32::consts::TAU;
+                let direction = [0.0, libm::cosf(angle), libm::sinf(angle)];
+                max_chroma[l * HUE_STEPS + h] = hull_distance(&points, origin, direction);
+            }
+        }
+
+        Self {
+            black,
+            white,
+            max_chroma,
+        }
+    }
+
+    /// Bilinear between the table entries, `position` is 0 at black and 1 at white
+    fn max_chroma(&self, position: f32, angle: f32) -> f32 {
+        let l = position.clamp(0.0, 1.0) * (LIGHTNESS_STEPS - 1) as f32;
+        let h = (angle / core::f32::consts::TAU).rem_euclid(1.0) * HUE_STEPS as f32;
+
+        let (l0, h0) = (l as usize, h as usize % HUE_STEPS);
+        let l1 = (l0 + 1).min(LIGHTNESS_STEPS - 1);
+        let h1 = (h0 + 1) % HUE_STEPS;
+        let (fl, fh) = (l - l0 as f32, h - libm::floorf(h));
+
+        let at = |l: usize, h: usize| self.max_chroma[l * HUE_STEPS + h];
+        let low = at(l0, h0) + (at(l0, h1) - at(l0, h0)) * fh;
+        let high = at(l1, h0) + (at(l1, h1) - at(l1, h0)) * fh;
+        low + (high - low) * fl
+    }
+}
+
+/// Point on the line from the black ink to the white ink, the panel's own gray axis
+fn axis(black: [f32; 3], white: [f32; 3], position: f32) -> [f32; 3] {
+    [0, 1, 2].map(|i| black[i] + (white[i] - black[i]) * position)
+}
+
+/// How far a ray from `origin` gets before leaving the hull of `points`. Every triangle of
+/// ink points lies inside the hull and the hull's faces are among them, so the farthest hit
+/// over all of them is where the ray leaves
+fn hull_distance(points: &[[f32; 3]], origin: [f32; 3], direction: [f32; 3]) -> f32 {
+    let mut farthest = 0.0f32;
+    for i in 0..points.len() {
+        for j in i + 1..points.len() {
+            for k in j + 1..points.len() {
+                if let Some(t) = ray_triangle(origin, direction, points[i], points[j], points[k]) {
+                    farthest = farthest.max(t);
+                }
+            }
+        }
+    }
+    farthest
+}
+
+/// Möller–Trumbore, distance along the ray or `None` when it misses
+fn ray_triangle(
+    origin: [f32; 3],
+    direction: [f32; 3],
+    a: [f32; 3],
+    b: [f32; 3],
+    c: [f32; 3],
+) -> Option<f32> {
+    let sub = |x: [f32; 3], y: [f32; 3]| [x[0] - y[0], x[1] - y[1], x[2] - y[2]];
+    let cross = |x: [f32; 3], y: [f32; 3]| {
+        [
+            x[1] * y[2] - x[2] * y[1],
+            x[2] * y[0] - x[0] * y[2],
+            x[0] * y[1] - x[1] * y[0],
+        ]
+    };
+    let dot = |x: [f32; 3], y: [f32; 3]| x[0] * y[0] + x[1] * y[1] + x[2] * y[2];
+
+    let edge1 = sub(b, a);
+    let edge2 = sub(c, a);
+    let p = cross(direction, edge2);
+    let det = dot(edge1, p);
+    if det.abs() < 1e-9 {
+        return None;
+    }
+
+    let inverse = 1.0 / det;
+    let s = sub(origin, a);
+    let u = dot(s, p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = cross(s, edge1);
+    let v = dot(direction, q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = dot(edge2, q) * inverse;
+    (t > 0.0).then_some(t)
+}
+
+/// Pulls colors inside what the inks can show. Chroma up to `knee` of the most the inks reach
+/// at that hue and lightness is left alone, beyond that it's squeezed into what's left
+pub struct GamutMap {
+    gamut: Gamut,
+    knee: f32,
+    to_linear: [f32; 256],
+    to_srgb: Vec<u8>,
+}
+
+impl GamutMap {
+    pub fn new(palette: &Palette, knee: f32) -> Self {
+        let mut to_linear = [0.0f32; 256];
+        for (i, v) in to_linear.iter_mut().enumerate() {
+            *v = srgb_to_linear(i as f32);
+        }
+
+        Self {
+            gamut: Gamut::new(palette),
+            knee: knee.clamp(0.0, 0.99),
+            to_linear,
+            to_srgb: (0..SRGB_STEPS)
+                .map(|i| (linear_to_srgb(i as f32 / (SRGB_STEPS - 1) as f32) + 0.5) as u8)
+                .collect(),
+        }
+    }
+
+    /// In place on a row of packed RGB888
+    pub fn row(&self, row: &mut [u8]) {
+        for p in row.chunks_exact_mut(3) {
+            self.pixel(p);
+        }
+    }
+
+    fn pixel(&self, p: &mut [u8]) {
+        let (gamut, to_linear) = (&self.gamut, &self.to_linear);
+        let [l, a, b] = linear_to_oklab([
+            to_linear[p[0] as usize],
+            to_linear[p[1] as usize],
+            to_linear[p[2] as usize],
+        ]);
+
+        let l = lightness(l, gamut.black[0], gamut.white[0]);
+        let position = ((l - gamut.black[0]) / (gamut.white[0] - gamut.black[0])).clamp(0.0, 1.0);
+        let origin = axis(gamut.black, gamut.white, position);
+        let (da, db) = (a - origin[1], b - origin[2]);
+        let chroma = libm::sqrtf(da * da + db * db);
+
+        let scale = if chroma > 1e-4 {
+            let limit = gamut.max_chroma(position, libm::atan2f(db, da));
+            compress(chroma, limit, self.knee) / chroma
+        } else {
+            1.0
+        };
+
+        let linear = oklab_to_linear([origin[0], origin[1] + da * scale, origin[2] + db * scale]);
+        for c in 0..3 {
+            let i = (linear[c].clamp(0.0, 1.0) * (SRGB_STEPS - 1) as f32 + 0.5) as usize;
+            p[c] = self.to_srgb[i];
+        }
+    }
+}
+
+/// Lightness between the inks' black and white is kept, anything darker or brighter is
+/// eased into what's left so shadows and highlights keep some detail
+fn lightness(l: f32, black: f32, white: f32) -> f32 {
+    let rolloff = LIGHTNESS_ROLLOFF.min((white - black) / 4.0);
+    let (low, high) = (black + rolloff, white - rolloff);
+    if l < low {
+        low - compress(low - l, rolloff, 0.0)
+    } else if l > high {
+        high + compress(l - high, rolloff, 0.0)
+    } else {
+        l
+    }
+}
+
+/// Soft knee, the curve flattens out towards `limit` and never quite reaches it
+fn compress(chroma: f32, limit: f32, knee: f32) -> f32 {
+    let start = limit * knee;
+    if chroma <= start {
+        return chroma;
+    }
+    let room = limit - start;
+    let over = chroma - start;
+    start + room * over / (over + room)
+}
@@ -0,0 +1,116 @@
+use alloc::vec;
+use epd_waveshare::prelude::HexColor;
+
+use super::{DecodedImage, Filter, Region, RowResizer, cover_crop};
+
+extern crate alloc;
+
+// What goes around a letterboxed photo instead of plain white bars
+
+/// Background is worked out at 1/BLUR_FACTOR of the panel and scaled back up, that's the blur
+const BLUR_FACTOR: usize = 16;
+/// Blurred background is pulled this far towards white so the photo stands out from it
+const BLUR_FADE: f32 = 0.35;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Mat {
+    /// One of the panel's own colors, no dithering needed
+    Solid(HexColor),
+    /// Most common color of the photo
+    Dominant,
+    /// Blurred and faded copy of the photo filling the panel
+    Blur,
+}
+
+impl Mat {
+    /// `solid` in `color`, `dominant` or `blur`
+    pub fn parse(style: &str, color: HexColor) -> Option<Self> {
+        match style {
+            "solid" => Some(Self::Solid(color)),
+            "dominant" => Some(Self::Dominant),
+            "blur" => Some(Self::Blur),
+            _ => None,
+        }
+    }
+}
+
+/// Panel color by name, e.g. `white` or `blue`
+pub fn palette_color(name: &str) -> Option<HexColor> {
+    match name {
+        "black" => Some(HexColor::Black),
+        "white" => Some(HexColor::White),
+        "yellow" => Some(HexColor::Yellow),
+        "red" => Some(HexColor::Red),
+        "blue" => Some(HexColor::Blue),
+        "green" => Some(HexColor::Green),
+        _ => None,
+    }
+}
+
+/// Most common color, counted in 4 bit per channel buckets and averaged inside the winner
+/// so it isn't snapped to the bucket's corner
+pub fn dominant_color(image: &DecodedImage) -> [u8; 3] {
+    let mut counts = vec![0u32; 16 * 16 * 16];
+    let bucket =
+        |p: &[u8]| ((p[0] as usize >> 4) << 8) | ((p[1] as usize >> 4) << 4) | (p[2] as usize >> 4);
+
+    // Every pixel isn't needed to find the most common color
+    let step = (image.pixels.len() / 3 / 65536).max(1);
+    for p in image.pixels.chunks_exact(3).step_by(step) {
+        counts[bucket(p)] += 1;
+    }
+
+    let winner = (0..counts.len()).max_by_key(|&i| counts[i]).unwrap_or(0);
+
+    let mut sum = [0u64; 3];
+    let mut n = 0u64;
+    for p in image.pixels.chunks_exact(3).step_by(step) {
+        if bucket(p) == winner {
+            sum[0] += p[0] as u64;
+            sum[1] += p[1] as u64;
+            sum[2] += p[2] as u64;
+            n += 1;
+        }
+    }
+
+    let n = n.max(1);
+    [(sum[0] / n) as u8, (sum[1] / n) as u8, (sum[2] / n) as u8]
+}
+
+/// Photo covering a panel of `width` x `height` at 1/BLUR_FACTOR the size, and faded.
+/// Scaling it up to the panel with a soft filter is what blurs it
+pub fn blurred(image: &DecodedImage, width: usize, height: usize) -> DecodedImage {
+    let (small_width, small_height) = ((width / BLUR_FACTOR).max(1), (height / BLUR_FACTOR).max(1));
+
+    // Middle of the photo with the panel's shape
+    let (crop_width, crop_height) = cover_crop(image.width, image.height, width, height);
+    let region = Region {
+        x: (image.width - crop_width) / 2,
+        y: (image.height - crop_height) / 2,
+        width: crop_width,
+        height: crop_height,
+    };
+
+    let mut pixels = vec![0u8; small_width * small_height * 3];
+    let mut resizer = RowResizer::new(
+        &image.pixels,
+        image.width,
+        region,
+        small_width,
+        small_height,
+        Filter::Box,
+    );
+    for (y, row) in pixels.chunks_exact_mut(small_width * 3).enumerate() {
+        resizer.row(y, row);
+    }
+
+    for v in pixels.iter_mut() {
+        *v = (*v as f32 + (255.0 - *v as f32) * BLUR_FADE) as u8;
+    }
+
+    DecodedImage {
+        pixels,
+        width: small_width,
+        height: small_height,
+    }
+}
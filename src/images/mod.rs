@@ -0,0 +1,95 @@
+extern crate alloc;
+
+use alloc::vec;
+use alloc::vec::Vec;
+use defmt::error;
+use zune_jpeg::JpegDecoder;
+use zune_jpeg::zune_core::bytestream::ZCursor;
+use zune_jpeg::zune_core::colorspace::ColorSpace;
+use zune_jpeg::zune_core::options::DecoderOptions;
+
+mod dither;
+mod fit;
+mod gamut;
+mod mat;
+mod palette;
+mod pipeline;
+mod resize;
+mod smart_crop;
+mod tone;
+
+pub use dither::{Dither, DitherOptions, Ditherer};
+pub use fit::{Anchor, Placement, ScaleMode, contain_size, cover_crop, placement};
+pub use frame_core::photo::Focus;
+pub use gamut::GamutMap;
+pub use mat::{Mat, blurred, dominant_color, palette_color};
+pub use palette::{
+    ColorSpace, MEASURED, Palette, linear_to_oklab, linear_to_srgb, oklab_to_linear, srgb_to_linear,
+};
+pub use pipeline::{RenderOptions, render};
+pub use resize::{Filter, Region, RowResizer};
+pub use smart_crop::smart_anchor;
+pub use tone::{Adjuster, Tone};
+
+/// Packed RGB888 pixels
+pub struct DecodedImage {
+    pub pixels: Vec<u8>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Most pixels we decode, 6 MB of RGB is what the 8 MB of PSRAM has room for next to the
+/// JPEG itself. A 1440px preview is well under, a camera original usually isn't
+pub const MAX_PIXELS: usize = 2_000_000;
+
+#[derive(Debug, defmt::Format)]
+pub enum DecodeError {
+    /// Corrupt or truncated JPEG
+    Jpeg,
+    /// Decoder could not convert the image to RGB (CMYK, odd subsampling, etc)
+    UnsupportedColorspace,
+    /// More than `MAX_PIXELS`, decoding it would run out of memory
+    TooLarge,
+}
+
+pub fn decode_jpeg(bytes: Vec<u8>) -> Result<DecodedImage, DecodeError> {
+    let options = DecoderOptions::default().jpeg_set_out_colorspace(ColorSpace::RGB);
+    let mut decoder = JpegDecoder::new_with_options(ZCursor::new(bytes), options);
+
+    // Size comes from the header, check it before anything is allocated for the pixels
+    decoder.decode_headers().map_err(|e| {
+        error!("[PIC] JPEG header failed: {}", defmt::Debug2Format(&e));
+        DecodeError::Jpeg
+    })?;
+    let info = decoder.info().ok_or(DecodeError::Jpeg)?;
+    if info.width as usize * info.height as usize > MAX_PIXELS {
+        error!(
+            "[PIC] {}x{} is too large to decode",
+            info.width, info.height
+        );
+        return Err(DecodeError::TooLarge);
+    }
+
+    let pixels = decoder.decode().map_err(|e| {
+        error!("[PIC] JPEG decode failed: {}", defmt::Debug2Format(&e));
+        DecodeError::Jpeg
+    })?;
+    let info = decoder.info().ok_or(DecodeError::Jpeg)?;
+
+    let (width, height) = (info.width as usize, info.height as usize);
+    if width == 0 || height == 0 || pixels.len() != width * height * 3 {
+        error!(
+            "[PIC] Expected RGB for {}x{} but got {} bytes",
+            width,
+            height,
+            pixels.len()
+        );
+        return Err(DecodeError::UnsupportedColorspace);
+    }
+
+    Ok(DecodedImage {
+        pixels,
+        width,
+        height,
+    })
+}
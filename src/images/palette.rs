@@ -0,0 +1,211 @@
+use epd_waveshare::prelude::HexColor;
+
+// The six inks as they actually look on the 7.3" Spectra 6 panel. `HexColor::rgb()` gives the
+// nominal colors, which are far brighter and more saturated than what the panel shows, so
+// dithering against them leaves photos washed out
+
+/// Measured values that get passed around for Spectra 6 panels, good enough for most of them.
+/// Individual panels vary a little, `PANEL_PALETTE` overrides any of these
+pub const MEASURED: [(HexColor, [u8; 3]); 6] = [
+    (HexColor::Black, [0x19, 0x1E, 0x21]),
+    (HexColor::White, [0xE8, 0xE8, 0xE8]),
+    (HexColor::Yellow, [0xEF, 0xDE, 0x44]),
+    (HexColor::Red, [0xB2, 0x13, 0x18]),
+    (HexColor::Blue, [0x21, 0x57, 0xBA]),
+    (HexColor::Green, [0x12, 0x5F, 0x20]),
+];
+
+/// Space the nearest ink is picked in
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub enum ColorSpace {
+    /// Newer and a bit more even for blues and purples
+    OkLab,
+    /// CIE 1976 L*a*b* with a D65 white
+    CieLab,
+}
+
+impl ColorSpace {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "oklab" => Some(Self::OkLab),
+            "cielab" => Some(Self::CieLab),
+            _ => None,
+        }
+    }
+
+    /// `rgb` is sRGB encoded 0-255
+    pub fn from_srgb(self, rgb: [f32; 3]) -> [f32; 3] {
+        self.from_linear(rgb.map(srgb_to_linear))
+    }
+
+    /// `linear` is linear light 0-1
+    pub fn from_linear(self, linear: [f32; 3]) -> [f32; 3] {
+        match self {
+            Self::OkLab => linear_to_oklab(linear),
+            Self::CieLab => linear_to_cielab(linear),
+        }
+    }
+}
+
+struct Ink {
+    color: HexColor,
+    /// sRGB 0-255
+    rgb: [f32; 3],
+    /// Linear light scaled to 0-255
+    linear: [f32; 3],
+    lab: [f32; 3],
+}
+
+pub struct Palette {
+    inks: [Ink; 6],
+    space: ColorSpace,
+}
+
+impl Palette {
+    /// `overrides` is `name=RRGGBB` pairs separated by `,`, e.g. `red=A01010,white=DDDDD8`.
+    /// `None` when it doesn't parse
+    pub fn new(space: ColorSpace, overrides: &str) -> Option<Self> {
+        let mut measured = MEASURED;
+
+        for pair in overrides
+            .split(',')
+            .map(str::trim)
+            .filter(|pair| !pair.is_empty())
+        {
+            let (name, hex) = pair.split_once('=')?;
+            let color = super::palette_color(name.trim())?;
+            let hex = hex.trim().trim_start_matches('#');
+            // `from_str_radix` on its own would take a leading `+`
+            if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                return None;
+            }
+            let value = u32::from_str_radix(hex, 16).ok()?;
+
+            let ink = measured.iter_mut().find(|(c, _)| *c == color)?;
+            ink.1 = [(value >> 16) as u8, (value >> 8) as u8, value as u8];
+        }
+
+        Some(Self {
+            inks: measured.map(|(color, rgb)| {
+                let rgb = rgb.map(|c| c as f32);
+                Ink {
+                    color,
+                    rgb,
+                    linear: rgb.map(|c| srgb_to_linear(c) * 255.0),
+                    lab: space.from_srgb(rgb),
+                }
+            }),
+            space,
+        })
+    }
+
+    /// Closest ink to `rgb` (sRGB 0-255) and what it really looks like on the panel
+    pub fn nearest(&self, rgb: [f32; 3]) -> (HexColor, [f32; 3]) {
+        let ink = self.closest(self.space.from_srgb(rgb.map(|c| c.clamp(0.0, 255.0))));
+        (ink.color, ink.rgb)
+    }
+
+    /// Same as `nearest` for linear light scaled to 0-255, and the ink comes back linear too
+    pub fn nearest_linear(&self, linear: [f32; 3]) -> (HexColor, [f32; 3]) {
+        let ink = self.closest(
+            self.space
+                .from_linear(linear.map(|c| (c / 255.0).clamp(0.0, 1.0))),
+        );
+        (ink.color, ink.linear)
+    }
+
+    /// Every ink and what it looks like, in linear light 0-1
+    pub fn inks(&self) -> impl Iterator<Item = (HexColor, [f32; 3])> + '_ {
+        self.inks
+            .iter()
+            .map(|ink| (ink.color, ink.linear.map(|c| c / 255.0)))
+    }
+
+    fn closest(&self, lab: [f32; 3]) -> &Ink {
+        let mut best = &self.inks[0];
+        let mut best_distance = f32::MAX;
+        for ink in &self.inks {
+            let d = [
+                lab[0] - ink.lab[0],
+                lab[1] - ink.lab[1],
+                lab[2] - ink.lab[2],
+            ];
+            let distance = d[0] * d[0] + d[1] * d[1] + d[2] * d[2];
+            if distance < best_distance {
+                best = ink;
+                best_distance = distance;
+            }
+        }
+
+        best
+    }
+}
+
+/// sRGB 0-255 to linear 0-1
+pub fn srgb_to_linear(c: f32) -> f32 {
+    let c = c / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        libm::powf((c + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Linear 0-1 to sRGB 0-255
+pub fn linear_to_srgb(c: f32) -> f32 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.003_130_8 {
+        c * 12.92
+    } else {
+        1.055 * libm::powf(c, 1.0 / 2.4) - 0.055
+    };
+    c * 255.0
+}
+
+pub fn linear_to_oklab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    let l = libm::cbrtf(0.412_221_46 * r + 0.536_332_55 * g + 0.051_445_995 * b);
+    let m = libm::cbrtf(0.211_903_5 * r + 0.680_699_5 * g + 0.107_396_96 * b);
+    let s = libm::cbrtf(0.088_302_46 * r + 0.281_718_85 * g + 0.629_978_7 * b);
+
+    [
+        0.210_454_26 * l + 0.793_617_8 * m - 0.004_072_047 * s,
+        1.977_998_5 * l - 2.428_592_2 * m + 0.450_593_7 * s,
+        0.025_904_037 * l + 0.782_771_77 * m - 0.808_675_77 * s,
+    ]
+}
+
+pub fn oklab_to_linear([l, a, b]: [f32; 3]) -> [f32; 3] {
+    let l_ = l + 0.396_337_78 * a + 0.215_803_76 * b;
+    let m_ = l - 0.105_561_346 * a - 0.063_854_17 * b;
+    let s_ = l - 0.089_484_18 * a - 1.291_485_5 * b;
+    let (l, m, s) = (l_ * l_ * l_, m_ * m_ * m_, s_ * s_ * s_);
+
+    [
+        4.076_741_7 * l - 3.307_711_6 * m + 0.230_969_94 * s,
+        -1.268_438 * l + 2.609_757_4 * m - 0.341_319_4 * s,
+        -0.004_196_086_3 * l - 0.703_418_6 * m + 1.707_614_7 * s,
+    ]
+}
+
+fn linear_to_cielab([r, g, b]: [f32; 3]) -> [f32; 3] {
+    // XYZ relative to the D65 white
+    let x = (0.412_456_4 * r + 0.357_576_1 * g + 0.180_437_5 * b) / 0.950_47;
+    let y = 0.212_672_9 * r + 0.715_152_2 * g + 0.072_175 * b;
+    let z = (0.019_333_9 * r + 0.119_192 * g + 0.950_304_1 * b) / 1.088_83;
+
+    let f = |t: f32| {
+        if t > 216.0 / 24389.0 {
+            libm::cbrtf(t)
+        } else {
+            (24389.0 / 27.0 * t + 16.0) / 116.0
+        }
+    };
+    let (fx, fy, fz) = (f(x), f(y), f(z));
+
+    // Scaled to about the same range as OKLab, only distances matter
+    [
+        (116.0 * fy - 16.0) / 100.0,
+        5.0 * (fx - fy),
+        2.0 * (fy - fz),
+    ]
+}
@@ -0,0 +1,214 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use super::{
+    Adjuster, DecodedImage, Dither, DitherOptions, Ditherer, Filter, Focus, GamutMap, Mat, Palette,
+    Region, RowResizer, ScaleMode, Tone, blurred, dominant_color, placement,
+};
+
+extern crate alloc;
+
+// Decoded photo to framebuffer. The photo itself is decoded whole, zune-jpeg has no row or
+// scaled output, and the tone histogram is taken over all of it. From scaling on it's a row at
+// a time: tone, the mat, gamut mapping and dithering each only hold the row they're on or a
+// couple around it, and dithered rows are packed straight into the display's buffer
+
+/// Everything about how a photo is drawn after it's decoded
+pub struct RenderOptions<'a> {
+    pub mode: ScaleMode,
+    pub filter: Filter,
+    /// Only used by `ScaleMode::Smart`
+    pub focus: Option<Focus>,
+    pub tone: Tone,
+    pub mat: Mat,
+    pub palette: &'a Palette,
+    /// `GamutMap` knee, `None` leaves colors as they are
+    pub gamut_knee: Option<f32>,
+    pub dither: Dither,
+    pub dither_options: DitherOptions,
+}
+
+/// What fills the rows around a letterboxed photo
+enum Background<'a> {
+    /// Nothing, the photo covers every row that's dithered
+    None,
+    Color([u8; 3]),
+    Blurred(RowResizer<'a>),
+}
+
+/// Draws `image` into `framebuffer`, which is a `width` x `height` panel at two pixels per
+/// byte with the left one in the high nibble. Returns where on the panel the photo went
+pub fn render(
+    image: &DecodedImage,
+    options: &RenderOptions,
+    framebuffer: &mut [u8],
+    width: usize,
+    height: usize,
+) -> Region {
+    let placed = placement(image, width, height, options.mode, options.focus);
+    let photo = Region {
+        x: (width - placed.width) / 2,
+        y: (height - placed.height) / 2,
+        width: placed.width,
+        height: placed.height,
+    };
+    let letterboxed = photo.width < width || photo.height < height;
+    let panel = Region::whole(width, height);
+
+    // Only scaled up a row at a time, so the tiny copy has to stay around
+    let blur = (letterboxed && options.mat == Mat::Blur).then(|| blurred(image, width, height));
+
+    // A solid mat is one of the inks already, only the photo needs dithering
+    let (area, mut background) = match (options.mat, &blur) {
+        _ if !letterboxed => (photo, Background::None),
+        (Mat::Solid(color), _) => {
+            let nibble = color.get_nibble();
+            framebuffer.fill((nibble << 4) | nibble);
+            (photo, Background::None)
+        }
+        (Mat::Dominant, _) => (panel, Background::Color(dominant_color(image))),
+        (Mat::Blur, Some(small)) => (
+            panel,
+            Background::Blurred(RowResizer::new(
+                &small.pixels,
+                small.width,
+                Region::whole(small.width, small.height),
+                width,
+                height,
+                Filter::Mitchell,
+            )),
+        ),
+        (Mat::Blur, None) => (photo, Background::None),
+    };
+
+    let mut photo_rows = PhotoRows::new(
+        RowResizer::new(
+            &image.pixels,
+            image.width,
+            placed.source,
+            photo.width,
+            photo.height,
+            options.filter,
+        ),
+        Adjuster::new(image, &options.tone),
+        photo.width,
+        photo.height,
+    );
+    let gamut = options
+        .gamut_knee
+        .map(|knee| GamutMap::new(options.palette, knee));
+    let mut ditherer = Ditherer::new(
+        area.width,
+        options.palette,
+        options.dither,
+        &options.dither_options,
+    );
+
+    let mut row = vec![0u8; area.width * 3];
+    let mut nibbles = vec![0u8; area.width];
+    let photo_start = (photo.x - area.x) * 3;
+
+    for y in area.y..area.y + area.height {
+        match &mut background {
+            Background::None => {}
+            Background::Color(color) => {
+                for p in row.chunks_exact_mut(3) {
+                    p.copy_from_slice(color);
+                }
+            }
+            Background::Blurred(resizer) => resizer.row(y, &mut row),
+        }
+
+        if (photo.y..photo.y + photo.height).contains(&y) {
+            photo_rows.next(&mut row[photo_start..photo_start + photo.width * 3]);
+        }
+
+        // Mat included, a blurred one has the same colors as the photo
+        if let Some(gamut) = &gamut {
+            gamut.row(&mut row);
+        }
+
+        ditherer.row(&row, &mut nibbles);
+        pack(framebuffer, width, area.x, y, &nibbles);
+    }
+
+    photo
+}
+
+/// Scaled and toned photo rows, top to bottom. Sharpening needs the rows on both sides so it
+/// works one row behind the scaler
+struct PhotoRows<'a> {
+    resizer: RowResizer<'a>,
+    adjuster: Adjuster,
+    height: usize,
+    /// Next row handed out
+    y: usize,
+    /// Rows above, at and below `y`, only filled when sharpening
+    window: [Vec<u8>; 3],
+}
+
+impl<'a> PhotoRows<'a> {
+    fn new(resizer: RowResizer<'a>, adjuster: Adjuster, width: usize, height: usize) -> Self {
+        let len = if adjuster.sharpens() { width * 3 } else { 0 };
+        Self {
+            resizer,
+            adjuster,
+            height,
+            y: 0,
+            window: [vec![0; len], vec![0; len], vec![0; len]],
+        }
+    }
+
+    fn next(&mut self, out: &mut [u8]) {
+        let y = self.y;
+        self.y += 1;
+
+        if !self.adjuster.sharpens() {
+            self.resizer.row(y, out);
+            self.adjuster.row(out);
+            return;
+        }
+
+        if y == 0 {
+            // Top edge repeats the first row
+            self.load(0);
+            let [above, current, first] = &mut self.window;
+            above.copy_from_slice(first);
+            current.copy_from_slice(first);
+            self.load(1);
+        }
+
+        let [above, current, below] = &self.window;
+        self.adjuster.sharpen(above, current, below, out);
+
+        self.window.rotate_left(1);
+        self.load(y + 2);
+    }
+
+    /// Row `y` into the bottom of the window, past the end repeats the last row
+    fn load(&mut self, y: usize) {
+        let [_, current, below] = &mut self.window;
+        if y < self.height {
+            self.resizer.row(y, below);
+            self.adjuster.row(below);
+        } else {
+            below.copy_from_slice(current);
+        }
+    }
+}
+
+/// Nibbles for `x..` of row `y` into the framebuffer, two pixels a byte and the left one in
+/// the high nibble
+fn pack(framebuffer: &mut [u8], width: usize, x: usize, y: usize, nibbles: &[u8]) {
+    let line = width.div_ceil(2);
+
+    for (i, &nibble) in nibbles.iter().enumerate() {
+        let x = x + i;
+        let byte = &mut framebuffer[y * line + x / 2];
+        *byte = if x % 2 == 0 {
+            (*byte & 0x0F) | (nibble << 4)
+        } else {
+            (*byte & 0xF0) | nibble
+        };
+    }
+}
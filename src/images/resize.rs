@@ -0,0 +1,257 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+extern crate alloc;
+
+// Separable resampler, one pass per axis. Downscaling widens the kernel by the reduction
+// ratio so every source pixel counts and big thumbnails don't alias.
+// Works one output row at a time: the source rows under the kernel are blended into a single
+// float row first, then that row is scaled across. Nothing bigger than a row is ever kept
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Filter {
+    /// Area average, sharp and cheap when shrinking, blocky when enlarging
+    Box,
+    /// Mitchell–Netravali with B = C = 1/3, soft without much ringing
+    Mitchell,
+    /// Sharpest, can ring a little around hard edges
+    Lanczos3,
+}
+
+impl Filter {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "box" => Some(Self::Box),
+            "mitchell" => Some(Self::Mitchell),
+            "lanczos3" => Some(Self::Lanczos3),
+            _ => None,
+        }
+    }
+
+    /// How far the kernel reaches at scale 1
+    fn support(self) -> f32 {
+        match self {
+            Self::Box => 0.5,
+            Self::Mitchell => 2.0,
+            Self::Lanczos3 => 3.0,
+        }
+    }
+
+    fn weight(self, x: f32) -> f32 {
+        let x = x.abs();
+        match self {
+            Self::Box => {
+                // Inclusive so enlarging never lands between two samples
+                if x <= 0.5 { 1.0 } else { 0.0 }
+            }
+            Self::Mitchell => {
+                const B: f32 = 1.0 / 3.0;
+                const C: f32 = 1.0 / 3.0;
+                if x < 1.0 {
+                    ((12.0 - 9.0 * B - 6.0 * C) * x * x * x
+                        + (-18.0 + 12.0 * B + 6.0 * C) * x * x
+                        + (6.0 - 2.0 * B))
+                        / 6.0
+                } else if x < 2.0 {
+                    ((-B - 6.0 * C) * x * x * x
+                        + (6.0 * B + 30.0 * C) * x * x
+                        + (-12.0 * B - 48.0 * C) * x
+                        + (8.0 * B + 24.0 * C))
+                        / 6.0
+                } else {
+                    0.0
+                }
+            }
+            Self::Lanczos3 => {
+                if x < 3.0 {
+                    sinc(x) * sinc(x / 3.0)
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x < 1e-6 {
+        return 1.0;
+    }
+    let x = x * core::f32::consts::PI;
+    libm::sinf(x) / x
+}
+
+/// Weights for every output pixel along one axis, all rows share them
+struct Weights {
+    /// First source pixel each output pixel reads
+    starts: Vec<usize>,
+    /// `taps` weights per output pixel, zero padded at the edges
+    weights: Vec<f32>,
+    taps: usize,
+}
+
+impl Weights {
+    fn new(filter: Filter, src_len: usize, dst_len: usize) -> Self {
+        let scale = src_len as f32 / dst_len as f32;
+        // Only widen when shrinking, enlarging samples the kernel as is
+        let stretch = scale.max(1.0);
+        let support = filter.support() * stretch;
+        let taps = (libm::ceilf(support) as usize) * 2 + 1;
+
+        let mut starts = Vec::with_capacity(dst_len);
+        let mut weights = vec![0.0; dst_len * taps];
+
+        for i in 0..dst_len {
+            let center = (i as f32 + 0.5) * scale - 0.5;
+            let first = libm::floorf(center - support + 1.0).max(0.0) as usize;
+            let last = (libm::floorf(center + support) as usize).min(src_len - 1);
+            let row = &mut weights[i * taps..(i + 1) * taps];
+
+            let mut total = 0.0;
+            for (tap, j) in (first..=last).take(taps).enumerate() {
+                let w = filter.weight((j as f32 - center) / stretch);
+                row[tap] = w;
+                total += w;
+            }
+
+            // Edges lose part of the kernel, normalizing keeps them from going dark
+            if total > 0.0 {
+                row.iter_mut().for_each(|w| *w /= total);
+            }
+
+            starts.push(first.min(src_len - 1));
+        }
+
+        Self {
+            starts,
+            weights,
+            taps,
+        }
+    }
+
+    /// One output pixel from `src`, `stride` is the distance between neighbours in values
+    fn apply<T: Copy + Into<f32>>(
+        &self,
+        i: usize,
+        src: &[T],
+        offset: usize,
+        stride: usize,
+        src_len: usize,
+    ) -> [u8; 3] {
+        let start = self.starts[i];
+        let row = &self.weights[i * self.taps..(i + 1) * self.taps];
+        let mut sum = [0.0f32; 3];
+
+        for (tap, &w) in row.iter().enumerate() {
+            let j = start + tap;
+            if w == 0.0 || j >= src_len {
+                continue;
+            }
+            let p = offset + j * stride;
+            sum[0] += src[p].into() * w;
+            sum[1] += src[p + 1].into() * w;
+            sum[2] += src[p + 2].into() * w;
+        }
+
+        sum.map(|v| (v + 0.5).clamp(0.0, 255.0) as u8)
+    }
+}
+
+/// Part of an image, in pixels
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub struct Region {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Region {
+    pub fn whole(width: usize, height: usize) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+}
+
+/// Scales `region` of a packed RGB888 image to `dst_width` x `dst_height`, one row per call
+pub struct RowResizer<'a> {
+    src: &'a [u8],
+    src_width: usize,
+    region: Region,
+    dst_width: usize,
+    /// `None` along an axis that isn't scaled, those rows and columns are copied as is
+    horizontal: Option<Weights>,
+    vertical: Option<Weights>,
+    /// Source rows blended together, `region.width` pixels
+    blended: Vec<f32>,
+}
+
+impl<'a> RowResizer<'a> {
+    pub fn new(
+        src: &'a [u8],
+        src_width: usize,
+        region: Region,
+        dst_width: usize,
+        dst_height: usize,
+        filter: Filter,
+    ) -> Self {
+        Self {
+            src,
+            src_width,
+            region,
+            dst_width,
+            horizontal: (region.width != dst_width)
+                .then(|| Weights::new(filter, region.width, dst_width)),
+            vertical: (region.height != dst_height)
+                .then(|| Weights::new(filter, region.height, dst_height)),
+            blended: vec![0.0; region.width * 3],
+        }
+    }
+
+    /// Output row `y` into `out`, which holds `dst_width` pixels
+    pub fn row(&mut self, y: usize, out: &mut [u8]) {
+        let stride = self.src_width * 3;
+        let first = self.region.x * 3;
+        let len = self.region.width * 3;
+
+        let Some(vertical) = &self.vertical else {
+            let start = (self.region.y + y) * stride + first;
+            let source = &self.src[start..start + len];
+            match &self.horizontal {
+                Some(horizontal) => {
+                    for (x, p) in out.chunks_exact_mut(3).take(self.dst_width).enumerate() {
+                        p.copy_from_slice(&horizontal.apply(x, source, 0, 3, self.region.width));
+                    }
+                }
+                None => out[..len].copy_from_slice(source),
+            }
+            return;
+        };
+
+        self.blended.fill(0.0);
+        let start = vertical.starts[y];
+        let weights = &vertical.weights[y * vertical.taps..(y + 1) * vertical.taps];
+        for (tap, &w) in weights.iter().enumerate() {
+            let row = start + tap;
+            if w == 0.0 || row >= self.region.height {
+                continue;
+            }
+            let from = (self.region.y + row) * stride + first;
+            for (sum, &v) in self.blended.iter_mut().zip(&self.src[from..from + len]) {
+                *sum += v as f32 * w;
+            }
+        }
+
+        for (x, p) in out.chunks_exact_mut(3).take(self.dst_width).enumerate() {
+            let rgb = match &self.horizontal {
+                Some(horizontal) => horizontal.apply(x, &self.blended, 0, 3, self.region.width),
+                None => [0, 1, 2].map(|c| (self.blended[x * 3 + c] + 0.5).clamp(0.0, 255.0) as u8),
+            };
+            p.copy_from_slice(&rgb);
+        }
+    }
+}
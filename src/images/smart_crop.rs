@@ -0,0 +1,134 @@
+use alloc::vec;
+use frame_core::photo::Focus;
+
+use super::{Anchor, DecodedImage, cover_crop};
+
+extern crate alloc;
+
+// Picks where `cover` crops so heads stay in. Uses face boxes from the source when there are
+// any, otherwise a rough saliency map: edges plus skin coloured pixels, summed along the
+// axis that gets cropped. Good enough to not cut off the one person in the photo
+
+/// Profile is built from at most this many samples along each side
+const SAMPLES: usize = 256;
+/// How much a skin coloured sample counts compared to edge energy
+const SKIN_WEIGHT: f32 = 60.0;
+/// Pull towards the middle so flat photos still crop centered
+const CENTER_BIAS: f32 = 0.25;
+
+/// Anchor for `ScaleMode::Cover` that keeps `focus`, or the busiest part of the photo without it
+pub fn smart_anchor(
+    image: &DecodedImage,
+    focus: Option<Focus>,
+    target_width: usize,
+    target_height: usize,
+) -> Anchor {
+    let (crop_width, crop_height) =
+        cover_crop(image.width, image.height, target_width, target_height);
+
+    // Only one axis ever gets cropped
+    let horizontal = crop_width < image.width;
+    let (len, window) = if horizontal {
+        (image.width, crop_width)
+    } else if crop_height < image.height {
+        (image.height, crop_height)
+    } else {
+        return Anchor::CENTER;
+    };
+
+    let offset = match focus {
+        Some(focus) => focus_offset(focus, horizontal, len, window),
+        None => saliency_offset(image, horizontal, window),
+    };
+
+    let along = offset.min(len - window) as f32 / (len - window) as f32;
+    if horizontal {
+        Anchor { x: along, y: 0.5 }
+    } else {
+        Anchor { x: 0.5, y: along }
+    }
+}
+
+fn focus_offset(focus: Focus, horizontal: bool, len: usize, window: usize) -> usize {
+    let (start, size) = if horizontal {
+        (focus.x, focus.width)
+    } else {
+        (focus.y, focus.height)
+    };
+    let start = start.clamp(0.0, 1.0) * len as f32;
+    let size = size.clamp(0.0, 1.0) * len as f32;
+    let window = window as f32;
+
+    let offset = if size <= window || horizontal {
+        start + size / 2.0 - window / 2.0
+    } else {
+        // A group taller than the window, keep the top with a bit of headroom since that's
+        // where the faces are
+        start - window * 0.05
+    };
+
+    offset.clamp(0.0, len as f32 - window) as usize
+}
+
+fn saliency_offset(image: &DecodedImage, horizontal: bool, window: usize) -> usize {
+    let (width, height) = (image.width, image.height);
+    let step = (width.max(height) / SAMPLES).max(1);
+    let len = if horizontal { width } else { height };
+    let bins = len.div_ceil(step);
+    let mut profile = vec![0.0f32; bins];
+
+    let pixel = |x: usize, y: usize| {
+        let i = (y * width + x) * 3;
+        let p = &image.pixels[i..i + 3];
+        (p[0] as f32, p[1] as f32, p[2] as f32)
+    };
+    let luma = |(r, g, b): (f32, f32, f32)| 0.299 * r + 0.587 * g + 0.114 * b;
+
+    for y in (0..height).step_by(step) {
+        for x in (0..width).step_by(step) {
+            let here = pixel(x, y);
+            let l = luma(here);
+            let right = luma(pixel((x + step).min(width - 1), y));
+            let down = luma(pixel(x, (y + step).min(height - 1)));
+
+            let mut score = (l - right).abs() + (l - down).abs();
+            if is_skin(here) {
+                score += SKIN_WEIGHT;
+            }
+
+            profile[if horizontal { x } else { y } / step] += score;
+        }
+    }
+
+    // Sliding window over the profile, best total wins
+    let window_bins = (window / step).clamp(1, bins);
+    let positions = bins - window_bins + 1;
+    let mut sum: f32 = profile[..window_bins].iter().sum();
+    let middle = (positions - 1) as f32 / 2.0;
+    // (position, score, distance from the middle), flat photos tie and end up centered
+    let mut best = (0, f32::MIN, f32::MAX);
+
+    for pos in 0..positions {
+        if pos > 0 {
+            sum += profile[pos + window_bins - 1] - profile[pos - 1];
+        }
+        let off_center = if middle > 0.0 {
+            (pos as f32 - middle).abs() / middle
+        } else {
+            0.0
+        };
+        let score = sum * (1.0 - CENTER_BIAS * off_center);
+        if score > best.1 || (score == best.1 && off_center < best.2) {
+            best = (pos, score, off_center);
+        }
+    }
+
+    best.0 * step
+}
+
+/// Classic RGB skin rule, catches most skin tones in daylight and not much else
+fn is_skin((r, g, b): (f32, f32, f32)) -> bool {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    r > 95.0 && g > 40.0 && b > 20.0 && max - min > 15.0 && (r - g).abs() > 15.0 && r > g && r > b
+}
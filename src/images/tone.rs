@@ -0,0 +1,211 @@
+use super::DecodedImage;
+
+// Tone and color tweaks between scaling and dithering. E-paper shows a lot less contrast and
+// color than a screen, so photos usually need a push to not look washed out.
+// Everything that works channel by channel is folded into one lookup table per channel
+
+/// Share of pixels auto levels lets clip at each end
+const LEVELS_CLIP: f32 = 0.005;
+
+#[derive(Clone, Copy, PartialEq, Debug, defmt::Format)]
+pub struct Tone {
+    /// Stretch the darkest and brightest pixels to black and white
+    pub auto_levels: bool,
+    /// S-curve strength, 0.0 is off and 1.0 the strongest
+    pub contrast: f32,
+    /// Above 1.0 brightens midtones, below darkens them
+    pub gamma: f32,
+    /// 1.0 leaves colors alone, 0.0 is grayscale
+    pub saturation: f32,
+    /// Saturation boost that goes easy on colors that are already strong, 0.0 is off
+    pub vibrance: f32,
+    /// Per channel multipliers, e.g. `[1.0, 1.0, 0.9]` to warm things up
+    pub white_balance: [f32; 3],
+    /// Unsharp mask amount, 0.0 is off
+    pub sharpen: f32,
+}
+
+impl Default for Tone {
+    fn default() -> Self {
+        Self {
+            auto_levels: false,
+            contrast: 0.0,
+            gamma: 1.0,
+            saturation: 1.0,
+            vibrance: 0.0,
+            white_balance: [1.0; 3],
+            sharpen: 0.0,
+        }
+    }
+}
+
+impl Tone {
+    /// `r,g,b` in percent like `100,100,90`, empty for none
+    pub fn parse_white_balance(value: &str) -> Option<[f32; 3]> {
+        if value.is_empty() {
+            return Some([1.0; 3]);
+        }
+
+        let mut parts = value.split(',').map(|part| part.trim().parse::<f32>().ok());
+        let balance = [parts.next()??, parts.next()??, parts.next()??];
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(balance.map(|c| c / 100.0))
+    }
+}
+
+/// `Tone` worked out for one photo, applied a row at a time
+pub struct Adjuster {
+    tables: [[u8; 256]; 3],
+    saturation: f32,
+    vibrance: f32,
+    sharpen: f32,
+    /// Nothing to do per pixel
+    identity: bool,
+}
+
+impl Adjuster {
+    /// Auto levels looks at the whole of `image`, not just the part that's shown
+    pub fn new(image: &DecodedImage, tone: &Tone) -> Self {
+        let identity = Tone {
+            sharpen: 0.0,
+            ..*tone
+        } == Tone::default();
+
+        Self {
+            tables: if identity {
+                [[0; 256]; 3]
+            } else {
+                channel_tables(image, tone)
+            },
+            saturation: tone.saturation,
+            vibrance: tone.vibrance,
+            sharpen: tone.sharpen,
+            identity,
+        }
+    }
+
+    /// Sharpening needs the rows above and below, see `sharpen`
+    pub fn sharpens(&self) -> bool {
+        self.sharpen > 0.0
+    }
+
+    /// Everything but sharpening, in place on a row of packed RGB888
+    pub fn row(&self, row: &mut [u8]) {
+        if self.identity {
+            return;
+        }
+
+        let boost = self.saturation != 1.0 || self.vibrance != 0.0;
+        for p in row.chunks_exact_mut(3) {
+            let mut rgb = [0, 1, 2].map(|c| self.tables[c][p[c] as usize] as f32);
+
+            if boost {
+                rgb = saturate(rgb, self.saturation, self.vibrance);
+            }
+
+            for c in 0..3 {
+                p[c] = rgb[c].clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+
+    /// Unsharp mask of `current` against a 3x3 box blur, into `out`. At the top and bottom
+    /// edge pass `current` again for the missing row
+    pub fn sharpen(&self, above: &[u8], current: &[u8], below: &[u8], out: &mut [u8]) {
+        let width = current.len() / 3;
+
+        for x in 0..width {
+            let left = x.saturating_sub(1);
+            let right = (x + 1).min(width - 1);
+
+            for c in 0..3 {
+                let mut sum = 0u32;
+                for row in [above, current, below] {
+                    sum += row[left * 3 + c] as u32
+                        + row[x * 3 + c] as u32
+                        + row[right * 3 + c] as u32;
+                }
+                let blurred = sum as f32 / 9.0;
+                let original = current[x * 3 + c] as f32;
+                let sharpened = original + (original - blurred) * self.sharpen;
+                out[x * 3 + c] = sharpened.clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// White balance, levels, gamma and contrast in that order
+fn channel_tables(image: &DecodedImage, tone: &Tone) -> [[u8; 256]; 3] {
+    let (low, high) = if tone.auto_levels {
+        levels(image, tone.white_balance)
+    } else {
+        (0.0, 255.0)
+    };
+    let range = (high - low).max(1.0);
+    let contrast = tone.contrast.clamp(0.0, 1.0);
+    let inverse_gamma = 1.0 / tone.gamma.max(0.01);
+
+    let mut tables = [[0u8; 256]; 3];
+    for (c, table) in tables.iter_mut().enumerate() {
+        for (i, v) in table.iter_mut().enumerate() {
+            let x = i as f32 * tone.white_balance[c];
+            let x = ((x - low) / range).clamp(0.0, 1.0);
+            let x = libm::powf(x, inverse_gamma);
+            // Blend towards smoothstep, steeper midtones without clipping the ends
+            let x = x + contrast * (x * x * (3.0 - 2.0 * x) - x);
+            *v = (x * 255.0 + 0.5) as u8;
+        }
+    }
+    tables
+}
+
+/// Values that `LEVELS_CLIP` of all channel values fall below and above, after white balance
+/// so a color cast doesn't stop the stretch
+fn levels(image: &DecodedImage, white_balance: [f32; 3]) -> (f32, f32) {
+    let mut histogram = [0u32; 256];
+    for p in image.pixels.chunks_exact(3) {
+        for c in 0..3 {
+            let v = (p[c] as f32 * white_balance[c]).clamp(0.0, 255.0) as usize;
+            histogram[v] += 1;
+        }
+    }
+
+    let total: u32 = histogram.iter().sum();
+    let clip = (total as f32 * LEVELS_CLIP) as u32;
+
+    let mut seen = 0;
+    let low = histogram
+        .iter()
+        .position(|&n| {
+            seen += n;
+            seen > clip
+        })
+        .unwrap_or(0);
+    seen = 0;
+    let high = 255
+        - histogram
+            .iter()
+            .rev()
+            .position(|&n| {
+                seen += n;
+                seen > clip
+            })
+            .unwrap_or(0);
+
+    if high <= low {
+        return (0.0, 255.0);
+    }
+    (low as f32, high as f32)
+}
+
+fn saturate(rgb: [f32; 3], saturation: f32, vibrance: f32) -> [f32; 3] {
+    let luma = 0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2];
+    let max = rgb[0].max(rgb[1]).max(rgb[2]);
+    let min = rgb[0].min(rgb[1]).min(rgb[2]);
+    let current = if max > 0.0 { (max - min) / max } else { 0.0 };
+
+    let factor = saturation * (1.0 + vibrance * (1.0 - current));
+    rgb.map(|c| luma + (c - luma) * factor)
+}
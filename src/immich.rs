@@ -0,0 +1,97 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{error, info};
+use frame_core::immich::parse_assets;
+use reqwless::headers::ContentType;
+
+use crate::http::{self, Endpoint, HttpClient};
+use crate::source::{Error, PhotoEntry, PhotoSource};
+
+extern crate alloc;
+
+// Immich https://immich.app/docs/api
+// Auth is an API key from Account Settings in the `x-api-key` header, it needs asset.read,
+// album.read and asset.view permissions
+
+/// How many photos to ask for when there's no album and we pick from the whole library
+const RANDOM_COUNT: u32 = 50;
+/// On the panel when the API key gets a 401
+const REJECTED: &str = "IMMICH REJECTED THE API KEY";
+
+pub struct ImmichSource {
+    client: HttpClient,
+    endpoint: Endpoint,
+    /// Album id from the album's URL, empty for random photos from the whole library
+    album_id: String,
+}
+
+impl ImmichSource {
+    pub fn new(client: HttpClient, endpoint: Endpoint, api_key: &str, album_id: &str) -> Self {
+        Self {
+            client,
+            endpoint: endpoint.with_header("x-api-key", api_key),
+            album_id: album_id.to_string(),
+        }
+    }
+
+    async fn list_album(&mut self) -> Result<Vec<u8>, Error> {
+        let url = self.endpoint.url(&format!(
+            "/api/albums/{}?withoutAssets=false",
+            self.album_id
+        ));
+
+        http::get(&mut self.client, &url, &self.endpoint.headers())
+            .await
+            .map_err(|e| Error::from_auth(e, REJECTED))
+    }
+
+    async fn list_random(&mut self) -> Result<Vec<u8>, Error> {
+        let url = self.endpoint.url("/api/search/random");
+        let body = format!("{{\"size\":{},\"type\":\"IMAGE\"}}", RANDOM_COUNT);
+
+        http::post(
+            &mut self.client,
+            &url,
+            &self.endpoint.headers(),
+            ContentType::ApplicationJson,
+            body.as_bytes(),
+        )
+        .await
+        .map_err(|e| Error::from_auth(e, REJECTED))
+    }
+}
+
+impl PhotoSource for ImmichSource {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        let data = if self.album_id.is_empty() {
+            self.list_random().await?
+        } else {
+            self.list_album().await?
+        };
+
+        let entries = parse_assets(&data).ok_or_else(|| {
+            error!("[IMM] Bad listing {:?}", core::str::from_utf8(&data).ok());
+            Error::InvalidResponse
+        })?;
+
+        info!("[IMM] {} photos", entries.len());
+
+        if entries.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        Ok(entries)
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        // Preview is a ~1440px JPEG, plenty for the panel
+        let url = self
+            .endpoint
+            .url(&format!("/api/assets/{}/thumbnail?size=preview", entry.id));
+
+        http::get(&mut self.client, &url, &self.endpoint.headers())
+            .await
+            .map_err(|e| Error::from_auth(e, REJECTED))
+    }
+}
@@ -1,5 +1,20 @@
 #![no_std]
 
+pub mod album_cache;
 pub mod battery;
+pub mod config;
+pub mod home_assistant;
+pub mod http;
+pub mod image_url;
 pub mod images;
+pub mod immich;
+pub mod mqtt;
+pub mod notify;
+pub mod retry;
+pub mod s3;
+pub mod sd_card;
+pub mod sntp;
+pub mod source;
+pub mod state;
 pub mod synology;
+pub mod webdav;
@@ -0,0 +1,146 @@
+use alloc::vec::Vec;
+use defmt::{info, warn};
+use embassy_net::dns::DnsQueryType;
+use embassy_net::tcp::TcpSocket;
+use embassy_time::{Duration, with_timeout};
+use embedded_io_async::{Read, Write};
+use frame_core::mqtt::{connect_packet, packet, parse_publish, publish_packet, subscribe_packet};
+
+extern crate alloc;
+
+pub use frame_core::mqtt::Message;
+
+// Bare bones MQTT 3.1.1, QoS 0 only. The frame is awake for a few seconds so it connects,
+// reads what's retained for it, says what it has to say and goes away. The packets are
+// `frame_core::mqtt`
+
+/// Socket timeout, a broker that goes quiet for this long is gone
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, defmt::Format)]
+pub enum Error {
+    Dns,
+    Connect,
+    /// Broker said no, `code` is the CONNACK return code
+    Refused(u8),
+    /// Connection dropped or timed out
+    Io,
+    /// Something that isn't MQTT or is bigger than our buffer
+    Protocol,
+}
+
+pub struct Client<'a> {
+    socket: TcpSocket<'a>,
+}
+
+impl<'a> Client<'a> {
+    pub async fn connect(
+        stack: embassy_net::Stack<'static>,
+        rx_buffer: &'a mut [u8],
+        tx_buffer: &'a mut [u8],
+        host: &str,
+        port: u16,
+        client_id: &str,
+        credentials: Option<(&str, &str)>,
+    ) -> Result<Self, Error> {
+        let address = *stack
+            .dns_query(host, DnsQueryType::A)
+            .await
+            .map_err(|_| Error::Dns)?
+            .first()
+            .ok_or(Error::Dns)?;
+
+        let mut socket = TcpSocket::new(stack, rx_buffer, tx_buffer);
+        socket.set_timeout(Some(TIMEOUT));
+        socket
+            .connect((address, port))
+            .await
+            .map_err(|_| Error::Connect)?;
+
+        let mut client = Self { socket };
+        client.send(&connect_packet(client_id, credentials)).await?;
+
+        let (kind, body) = client.receive().await?;
+        if kind >> 4 != 2 || body.len() < 2 {
+            return Err(Error::Protocol);
+        }
+        if body[1] != 0 {
+            warn!("[MQTT] Broker refused with {}", body[1]);
+            return Err(Error::Refused(body[1]));
+        }
+
+        info!("[MQTT] Connected to {}", host);
+        Ok(client)
+    }
+
+    pub async fn publish(
+        &mut self,
+        topic: &str,
+        payload: &[u8],
+        retain: bool,
+    ) -> Result<(), Error> {
+        self.send(&publish_packet(topic, payload, retain)).await
+    }
+
+    pub async fn subscribe(&mut self, topics: &[&str]) -> Result<(), Error> {
+        self.send(&subscribe_packet(topics)).await
+    }
+
+    /// Next message from a subscription, `None` once the broker has been quiet for `quiet`.
+    /// Retained messages show up right after the SUBACK so a short wait is enough to get them all
+    pub async fn next_message(&mut self, quiet: Duration) -> Result<Option<Message>, Error> {
+        loop {
+            let (kind, body) = match with_timeout(quiet, self.receive()).await {
+                Ok(packet) => packet?,
+                Err(_) => return Ok(None),
+            };
+
+            // Anything but PUBLISH (SUBACK, PINGRESP) is of no interest
+            if kind >> 4 != 3 {
+                continue;
+            }
+
+            return parse_publish(kind, &body).map(Some).ok_or(Error::Protocol);
+        }
+    }
+
+    pub async fn disconnect(mut self) {
+        let _ = self.send(&packet(0xE0, &[])).await;
+        let _ = self.socket.flush().await;
+        self.socket.close();
+    }
+
+    async fn send(&mut self, packet: &[u8]) -> Result<(), Error> {
+        self.socket.write_all(packet).await.map_err(|_| Error::Io)?;
+        self.socket.flush().await.map_err(|_| Error::Io)
+    }
+
+    /// Fixed header type byte and the rest of the packet
+    async fn receive(&mut self) -> Result<(u8, Vec<u8>), Error> {
+        let mut byte = [0u8; 1];
+        self.read_exact(&mut byte).await?;
+        let kind = byte[0];
+
+        let mut len = 0usize;
+        for shift in (0..28).step_by(7) {
+            self.read_exact(&mut byte).await?;
+            len |= ((byte[0] & 0x7F) as usize) << shift;
+            if byte[0] & 0x80 == 0 {
+                break;
+            }
+        }
+
+        // Retained photos aren't a thing, anything this big is a mistake
+        if len > 16 * 1024 {
+            return Err(Error::Protocol);
+        }
+
+        let mut body = alloc::vec![0u8; len];
+        self.read_exact(&mut body).await?;
+        Ok((kind, body))
+    }
+
+    async fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), Error> {
+        self.socket.read_exact(buf).await.map_err(|_| Error::Io)
+    }
+}
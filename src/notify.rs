@@ -0,0 +1,117 @@
+use alloc::format;
+use alloc::string::ToString;
+use defmt::info;
+use reqwless::headers::ContentType;
+use serde_json::json;
+
+use crate::http::{self, HttpClient};
+
+extern crate alloc;
+
+// Push notification when the battery runs low, so someone hears about it before walking past
+// a frame that's been showing "I NEEDS A CHARGE" for a week
+
+pub enum Service {
+    /// `NOTIFY_URL` is the topic, e.g. `https://ntfy.sh/my-frame`
+    Ntfy,
+    /// `NOTIFY_URL` is the server, the token is an application token
+    Gotify,
+    /// JSON POST to `NOTIFY_URL`, for Home Assistant webhooks, n8n and friends
+    Webhook,
+}
+
+impl Service {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ntfy" => Some(Self::Ntfy),
+            "gotify" => Some(Self::Gotify),
+            "webhook" => Some(Self::Webhook),
+            _ => None,
+        }
+    }
+}
+
+pub struct LowBattery<'a> {
+    pub device: &'a str,
+    pub percent: i8,
+    pub mv: u16,
+}
+
+pub async fn low_battery(
+    client: &mut HttpClient,
+    service: &Service,
+    url: &str,
+    token: Option<&str>,
+    battery: &LowBattery<'_>,
+) -> Result<(), http::Error> {
+    let title = format!("{} needs a charge", battery.device);
+    let message = format!(
+        "Battery is at {}% ({:.2}V)",
+        battery.percent,
+        battery.mv as f32 / 1000.0
+    );
+
+    match service {
+        Service::Ntfy => {
+            let auth = token.map(|token| format!("Bearer {}", token));
+            let mut headers = alloc::vec![
+                ("Title", title.as_str()),
+                ("Tags", "battery"),
+                ("Priority", "high"),
+            ];
+            if let Some(auth) = auth.as_deref() {
+                headers.push(("Authorization", auth));
+            }
+            http::post(
+                client,
+                url,
+                &headers,
+                ContentType::TextPlain,
+                message.as_bytes(),
+            )
+            .await?;
+        }
+        Service::Gotify => {
+            let url = format!("{}/message", url.trim_end_matches('/'));
+            let body = json!({
+                "title": title,
+                "message": message,
+                "priority": 8,
+            });
+            http::post(
+                client,
+                &url,
+                &[("X-Gotify-Key", token.unwrap_or(""))],
+                ContentType::ApplicationJson,
+                body.to_string().as_bytes(),
+            )
+            .await?;
+        }
+        Service::Webhook => {
+            let auth = token.map(|token| format!("Bearer {}", token));
+            let headers: alloc::vec::Vec<(&str, &str)> = auth
+                .as_deref()
+                .map(|auth| ("Authorization", auth))
+                .into_iter()
+                .collect();
+            let body = json!({
+                "event": "low_battery",
+                "device": battery.device,
+                "battery": battery.percent,
+                "voltage": battery.mv as f32 / 1000.0,
+                "message": message,
+            });
+            http::post(
+                client,
+                url,
+                &headers,
+                ContentType::ApplicationJson,
+                body.to_string().as_bytes(),
+            )
+            .await?;
+        }
+    }
+
+    info!("[BAT] Low battery notification sent");
+    Ok(())
+}
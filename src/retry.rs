@@ -0,0 +1,66 @@
+use defmt::warn;
+use embassy_time::{Duration, Instant, Timer, with_deadline};
+
+/// Errors that know whether trying again could help
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+}
+
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u8,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl RetryPolicy {
+    pub const DEFAULT: Self = Self {
+        max_attempts: 4,
+        initial_backoff: Duration::from_secs(1),
+        max_backoff: Duration::from_secs(8),
+    };
+}
+
+/// Runs `op` until it succeeds, fails with something that isn't retryable, runs out of attempts
+/// or the next attempt would start past `deadline`. Each attempt is also cut off at `deadline`
+pub async fn retry<T, E>(
+    policy: &RetryPolicy,
+    deadline: Instant,
+    label: &str,
+    mut op: impl AsyncFnMut() -> Result<T, E>,
+) -> Result<T, E>
+where
+    E: Retryable + From<embassy_time::TimeoutError> + defmt::Format,
+{
+    let mut backoff = policy.initial_backoff;
+    let mut attempt = 1;
+
+    loop {
+        let err = match with_deadline(deadline, op()).await {
+            Ok(Ok(value)) => return Ok(value),
+            Ok(Err(e)) => e,
+            Err(timeout) => E::from(timeout),
+        };
+
+        if !err.is_retryable() || attempt >= policy.max_attempts {
+            return Err(err);
+        }
+
+        if Instant::now() + backoff >= deadline {
+            warn!("[RETRY] {} out of time: {:?}", label, err);
+            return Err(err);
+        }
+
+        warn!(
+            "[RETRY] {} attempt {} failed: {:?}, retry in {}ms",
+            label,
+            attempt,
+            err,
+            backoff.as_millis()
+        );
+
+        Timer::after(backoff).await;
+        backoff = (backoff * 2).min(policy.max_backoff);
+        attempt += 1;
+    }
+}
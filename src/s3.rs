@@ -0,0 +1,206 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{error, info};
+use frame_core::s3::{
+    EMPTY_SHA256, Rejection, amz_date, authorization, canonical_request, error_code, rejection,
+    uri_encode,
+};
+use frame_core::xml;
+
+use crate::http::{self, Endpoint, HttpClient};
+use crate::sntp;
+use crate::source::{Error, MAX_PHOTOS, PhotoEntry, PhotoSource, is_jpeg};
+
+extern crate alloc;
+
+// S3 compatible object storage, e.g. MinIO. Path style URLs (`{endpoint}/{bucket}/{key}`)
+// since that's what MinIO and most self hosted stores want. Requests are signed with SigV4,
+// which needs the real date so the time comes from NTP first
+
+pub struct S3Source {
+    client: HttpClient,
+    stack: embassy_net::Stack<'static>,
+    /// Base is the server, e.g. `https://minio.example.com:9000`
+    endpoint: Endpoint,
+    bucket: String,
+    prefix: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    ntp_server: String,
+    /// Unix time from NTP and the `Instant` we got it at
+    clock: Option<(u64, embassy_time::Instant)>,
+}
+
+impl S3Source {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        client: HttpClient,
+        stack: embassy_net::Stack<'static>,
+        endpoint: Endpoint,
+        bucket: &str,
+        prefix: &str,
+        region: &str,
+        access_key: &str,
+        secret_key: &str,
+        ntp_server: &str,
+    ) -> Self {
+        Self {
+            client,
+            stack,
+            endpoint,
+            bucket: bucket.to_string(),
+            prefix: prefix.trim_start_matches('/').to_string(),
+            region: region.to_string(),
+            access_key: access_key.to_string(),
+            secret_key: secret_key.to_string(),
+            ntp_server: ntp_server.to_string(),
+            clock: None,
+        }
+    }
+
+    async fn now(&mut self) -> Result<u64, Error> {
+        if self.clock.is_none() {
+            let unix = sntp::unix_time(self.stack, &self.ntp_server)
+                .await
+                .map_err(|e| {
+                    error!("[S3] No time from NTP: {:?}", e);
+                    Error::NoTime
+                })?;
+            self.clock = Some((unix, embassy_time::Instant::now()));
+        }
+
+        let (unix, at) = self.clock.unwrap();
+        Ok(unix + at.elapsed().as_secs())
+    }
+
+    /// Signed GET, `path` and `query` are already URI encoded and `query` is sorted by name
+    async fn get(&mut self, path: &str, query: &str) -> Result<Vec<u8>, Error> {
+        let now = self.now().await?;
+        let amz_date = amz_date(now);
+
+        let mut url = self.endpoint.url(path);
+        if !query.is_empty() {
+            url = format!("{}?{}", url, query);
+        }
+        let host = url::Url::parse(&url)
+            .ok()
+            .and_then(|url| http::host(&url))
+            .ok_or(Error::Http(http::Error::InvalidUrl))?;
+
+        let authorization = authorization(
+            &self.access_key,
+            &self.secret_key,
+            &self.region,
+            &amz_date,
+            &canonical_request(path, query, &host, &amz_date),
+        );
+
+        let mut headers = self.endpoint.headers();
+        headers.push(("x-amz-content-sha256", EMPTY_SHA256));
+        headers.push(("x-amz-date", &amz_date));
+        headers.push(("Authorization", &authorization));
+
+        let reply = http::send_raw(&mut self.client, "GET", &url, &headers, &[]).await?;
+        if (200..300).contains(&reply.status) {
+            return Ok(reply.body);
+        }
+
+        let code = error_code(&reply.body);
+        error!("[S3] {} {:?}", reply.status, code);
+
+        let rejection = rejection(code);
+        // Clock drifted or NTP gave us a bad time, ask again on the retry
+        if rejection == Some(Rejection::Clock) {
+            self.clock = None;
+        }
+        Err(status_error(reply.status, rejection))
+    }
+}
+
+impl PhotoSource for S3Source {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        let path = format!("/{}", uri_encode(&self.bucket, true));
+        let mut entries = Vec::new();
+        let mut continuation: Option<String> = None;
+
+        loop {
+            // Sorted by name, the signature depends on it
+            let mut query = String::new();
+            if let Some(token) = &continuation {
+                query.push_str(&format!("continuation-token={}&", uri_encode(token, false)));
+            }
+            query.push_str(&format!(
+                "list-type=2&max-keys=1000&prefix={}",
+                uri_encode(&self.prefix, false)
+            ));
+
+            let data = self.get(&path, &query).await?;
+            let data = core::str::from_utf8(&data).map_err(|_| Error::InvalidResponse)?;
+
+            for contents in xml::elements(data, "Contents") {
+                let Some(key) = xml::first(contents, "Key").map(xml::unescape) else {
+                    continue;
+                };
+                if is_jpeg(&key) && entries.len() < MAX_PHOTOS {
+                    entries.push(PhotoEntry {
+                        name: key.rsplit('/').next().map(ToString::to_string),
+                        id: key,
+                        key: None,
+                        taken_at: None,
+                        focus: None,
+                    });
+                }
+            }
+
+            continuation = match xml::first(data, "IsTruncated") {
+                Some("true") if entries.len() < MAX_PHOTOS => {
+                    xml::first(data, "NextContinuationToken").map(xml::unescape)
+                }
+                _ => None,
+            };
+            if continuation.is_none() {
+                break;
+            }
+        }
+
+        info!("[S3] {} photos", entries.len());
+
+        if entries.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        Ok(entries)
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        let path = format!(
+            "/{}/{}",
+            uri_encode(&self.bucket, true),
+            uri_encode(&entry.id, true)
+        );
+        self.get(&path, "").await
+    }
+}
+
+/// Only a bad key stops the frame
+fn status_error(status: u16, rejection: Option<Rejection>) -> Error {
+    match rejection {
+        Some(Rejection::Credentials) => Error::CredentialsRejected {
+            code: status,
+            message: "S3 REJECTED THE ACCESS KEY",
+        },
+        Some(Rejection::Clock) => Error::Api {
+            code: status,
+            message: "S3 SAYS THE CLOCK IS WRONG",
+            retryable: true,
+        },
+        Some(Rejection::Denied) => Error::Api {
+            code: status,
+            message: "S3 DENIED ACCESS",
+            retryable: false,
+        },
+        _ => http::Error::Status(status).into(),
+    }
+}
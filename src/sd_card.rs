@@ -0,0 +1,149 @@
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use defmt::{info, warn};
+use embedded_sdmmc::BlockDevice;
+use frame_core::sd_card;
+
+use crate::http::{self, MAX_BODY_LEN};
+use crate::source::{Error, MAX_PHOTOS, PhotoEntry, PhotoSource, random_index};
+use crate::state;
+
+pub use frame_core::sd_card::SdCard;
+
+extern crate alloc;
+
+// Photo sources on the microSD card, the FAT side is `frame_core::sd_card`
+
+impl From<sd_card::Error> for Error {
+    fn from(e: sd_card::Error) -> Self {
+        match e {
+            sd_card::Error::NotFound => Error::NotFound,
+            sd_card::Error::TooLarge => Error::Http(http::Error::TooLarge),
+            sd_card::Error::Storage(reason) => {
+                warn!("[SD] {}", reason.as_str());
+                Error::Storage
+            }
+        }
+    }
+}
+
+/// JPEGs in one folder on the card, works without any network
+pub struct SdFolder<'a, D: BlockDevice> {
+    card: &'a mut SdCard<D>,
+    path: &'a str,
+}
+
+impl<'a, D: BlockDevice> SdFolder<'a, D> {
+    pub fn new(card: &'a mut SdCard<D>, path: &'a str) -> Self {
+        Self { card, path }
+    }
+}
+
+impl<D: BlockDevice> PhotoSource for SdFolder<'_, D> {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        let names = self.card.list_jpegs(self.path)?;
+        info!("[SD] {} photos in {}", names.len(), self.path);
+
+        if names.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        Ok(names
+            .into_iter()
+            .take(MAX_PHOTOS)
+            .map(|name| PhotoEntry {
+                id: name.clone(),
+                key: None,
+                name: Some(name),
+                taken_at: None,
+                focus: None,
+            })
+            .collect())
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        Ok(self.card.read(self.path, &entry.id, MAX_BODY_LEN)?)
+    }
+}
+
+/// Wraps a network source and keeps a copy of every photo it fetches on the card,
+/// so later wakes can show one of those when the network is down
+pub struct SdCached<'a, S, D: BlockDevice> {
+    inner: S,
+    card: &'a mut SdCard<D>,
+    path: &'a str,
+    /// Oldest photos aren't tracked, a random one makes room once there are this many
+    max_photos: usize,
+}
+
+impl<'a, S: PhotoSource, D: BlockDevice> SdCached<'a, S, D> {
+    pub fn new(inner: S, card: &'a mut SdCard<D>, path: &'a str, max_photos: usize) -> Self {
+        Self {
+            inner,
+            card,
+            path,
+            max_photos,
+        }
+    }
+
+    fn store(&mut self, entry: &PhotoEntry, data: &[u8]) -> Result<(), Error> {
+        // 8.3 only so ids become a hash
+        let name = format!("{:08X}.JPG", state::fingerprint(&[&entry.id]));
+
+        // Missing folder just means nothing is cached yet, `write` makes it
+        let present = self.card.exists(self.path, &name).unwrap_or(false);
+        if present && self.inner.stable_ids() {
+            return Ok(());
+        }
+
+        // Written before anything is evicted, a failed write shouldn't cost us a photo
+        self.card.write(self.path, &name, data)?;
+        info!("[SD] Cached {} as {}", entry.id.as_str(), name.as_str());
+
+        // Replacing a copy takes no extra room
+        if present || self.max_photos == 0 {
+            return Ok(());
+        }
+        let mut others = self.card.list_jpegs(self.path)?;
+        others.retain(|other| *other != name);
+        while others.len() >= self.max_photos {
+            let victim = others.swap_remove(random_index(others.len()));
+            self.card.delete(self.path, &victim)?;
+        }
+        Ok(())
+    }
+}
+
+impl<S: PhotoSource, D: BlockDevice> PhotoSource for SdCached<'_, S, D> {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        self.inner.list().await
+    }
+
+    fn select(&mut self, entries: &[PhotoEntry]) -> usize {
+        self.inner.select(entries)
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        let data = self.inner.fetch(entry).await?;
+
+        // A full or missing card shouldn't cost us the photo we just got
+        if let Err(e) = self.store(entry, &data) {
+            warn!("[SD] Could not cache {}: {:?}", entry.id.as_str(), e);
+        }
+
+        Ok(data)
+    }
+
+    fn session(&self) -> Option<&str> {
+        self.inner.session()
+    }
+
+    fn restore_session(&mut self, session: String) {
+        self.inner.restore_session(session)
+    }
+
+    fn stable_ids(&self) -> bool {
+        self.inner.stable_ids()
+    }
+}
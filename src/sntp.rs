@@ -0,0 +1,66 @@
+use defmt::{info, warn};
+use embassy_net::dns::DnsQueryType;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_time::{Duration, with_timeout};
+
+// Just enough SNTP to know what time it is, nothing on the frame keeps wall clock time
+// through a power loss. Only needed by things that sign requests with the date
+
+/// Seconds between 1900 (NTP) and 1970 (unix)
+const NTP_TO_UNIX: u64 = 2_208_988_800;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Debug, defmt::Format)]
+pub enum Error {
+    Dns,
+    Socket,
+    Timeout,
+    InvalidResponse,
+}
+
+/// Current unix time in seconds from `server`
+pub async fn unix_time(stack: embassy_net::Stack<'static>, server: &str) -> Result<u64, Error> {
+    let addresses = stack
+        .dns_query(server, DnsQueryType::A)
+        .await
+        .map_err(|_| Error::Dns)?;
+    let address = *addresses.first().ok_or(Error::Dns)?;
+
+    let mut rx_meta = [PacketMetadata::EMPTY; 1];
+    let mut rx_buffer = [0u8; 64];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0u8; 64];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    socket.bind(0).map_err(|_| Error::Socket)?;
+
+    // LI 0, version 4, mode 3 (client), everything else can be zero
+    let mut packet = [0u8; 48];
+    packet[0] = 0x23;
+
+    socket
+        .send_to(&packet, (address, 123))
+        .await
+        .map_err(|_| Error::Socket)?;
+
+    let (len, _) = with_timeout(TIMEOUT, socket.recv_from(&mut packet))
+        .await
+        .map_err(|_| Error::Timeout)?
+        .map_err(|_| Error::Socket)?;
+
+    // Transmit timestamp, whole seconds
+    let seconds = u32::from_be_bytes([packet[40], packet[41], packet[42], packet[43]]) as u64;
+    if len < 48 || seconds < NTP_TO_UNIX {
+        warn!("[NTP] Bad response from {}", server);
+        return Err(Error::InvalidResponse);
+    }
+
+    let now = seconds - NTP_TO_UNIX;
+    info!("[NTP] Unix time {}", now);
+    Ok(now)
+}
@@ -0,0 +1,307 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{info, warn};
+use embassy_time::Instant;
+use embedded_storage::Storage;
+use serde::{Deserialize, Serialize};
+
+use crate::album_cache::AlbumCache;
+use crate::http;
+use crate::image_url::ImageUrlSource;
+use crate::images::{DecodeError, DecodedImage, decode_jpeg};
+use crate::immich::ImmichSource;
+use crate::retry::{RetryPolicy, Retryable, retry};
+use crate::s3::S3Source;
+use crate::synology::{FileStationSource, SynologySource};
+use crate::webdav::WebDavSource;
+
+pub use frame_core::photo::{MAX_PHOTOS, PhotoEntry, is_jpeg};
+
+extern crate alloc;
+
+/// How many different photos to try before giving up on this wake
+const MAX_PHOTO_ATTEMPTS: usize = 3;
+
+pub struct Photo {
+    pub image: DecodedImage,
+    pub entry: PhotoEntry,
+}
+
+#[derive(Debug, defmt::Format)]
+pub enum Error {
+    Http(http::Error),
+    /// Server refused, `message` is short enough for the panel
+    Api {
+        code: u16,
+        message: &'static str,
+        retryable: bool,
+    },
+    /// Logging in again won't help and might get us blocked
+    CredentialsRejected {
+        code: u16,
+        message: &'static str,
+    },
+    /// Photo or album doesn't exist (anymore)
+    NotFound,
+    /// Response didn't look like what we expected
+    InvalidResponse,
+    /// Nothing to show
+    Empty,
+    Decode(DecodeError),
+    /// SD card missing, unreadable or not FAT
+    Storage,
+    /// Couldn't get the time from NTP, needed to sign requests
+    NoTime,
+    /// `PHOTO_SOURCE` isn't a backend we know
+    UnknownSource,
+}
+
+impl Retryable for Error {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Error::Http(e) => e.is_retryable(),
+            Error::Api { retryable, .. } => *retryable,
+            Error::NoTime => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(e: http::Error) -> Self {
+        match e {
+            http::Error::Status(404) => Error::NotFound,
+            e => Error::Http(e),
+        }
+    }
+}
+
+impl From<embassy_time::TimeoutError> for Error {
+    fn from(e: embassy_time::TimeoutError) -> Self {
+        Error::Http(e.into())
+    }
+}
+
+impl Error {
+    /// Short uppercase message that fits on the panel
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::Http(http::Error::Request(_)) => "COULD NOT REACH THE SERVER",
+            Error::Http(http::Error::Status(403)) => "SERVER DENIED ACCESS",
+            Error::Http(http::Error::Status(_)) => "SERVER RETURNED AN HTTP ERROR",
+            Error::Http(http::Error::TooLarge) => "PHOTO IS TOO LARGE",
+            Error::Http(http::Error::Timeout) => "TIMED OUT TALKING TO THE SERVER",
+            Error::Http(http::Error::InvalidUrl) => "INVALID SERVER URL IN CONFIG",
+            Error::Api { message, .. } | Error::CredentialsRejected { message, .. } => message,
+            Error::NotFound => "PHOTO OR ALBUM NOT FOUND",
+            Error::InvalidResponse => "UNEXPECTED RESPONSE FROM THE SERVER",
+            Error::Empty => "NO PHOTOS TO SHOW",
+            Error::Decode(DecodeError::TooLarge) => "PHOTO HAS TOO MANY PIXELS",
+            Error::Decode(_) => "PHOTO COULD NOT BE DECODED",
+            Error::Storage => "COULD NOT READ THE SD CARD",
+            Error::NoTime => "COULD NOT GET THE TIME FROM NTP",
+            Error::UnknownSource => "UNKNOWN PHOTO_SOURCE IN CONFIG",
+        }
+    }
+
+    /// For backends that send their credentials with every request. Only a 401 means they're
+    /// wrong, a 403 can just as well be one file or folder the account can't read
+    pub fn from_auth(e: http::Error, message: &'static str) -> Self {
+        match e {
+            http::Error::Status(code @ 401) => Error::CredentialsRejected { code, message },
+            e => e.into(),
+        }
+    }
+}
+
+/// Somewhere photos come from. `main` only talks to this so backends can be swapped by config
+#[allow(async_fn_in_trait)]
+pub trait PhotoSource {
+    /// Everything that could be shown
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error>;
+
+    /// Index into `entries` of the photo to show next, `entries` is never empty
+    fn select(&mut self, entries: &[PhotoEntry]) -> usize {
+        random_index(entries.len())
+    }
+
+    /// Encoded image bytes
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error>;
+
+    /// Login session worth keeping next to a cached listing
+    fn session(&self) -> Option<&str> {
+        None
+    }
+
+    fn restore_session(&mut self, _session: String) {}
+
+    /// Same id means the same bytes, so a copy kept from an earlier wake is still good
+    fn stable_ids(&self) -> bool {
+        true
+    }
+}
+
+pub fn random_index(len: usize) -> usize {
+    let rand = esp_hal::rng::Rng::new().random();
+    (rand as usize) % len
+}
+
+/// Every backend the frame knows about, picked by `PHOTO_SOURCE`
+pub enum Backend {
+    Synology(SynologySource),
+    FileStation(FileStationSource),
+    Immich(ImmichSource),
+    ImageUrl(ImageUrlSource),
+    WebDav(WebDavSource),
+    S3(S3Source),
+}
+
+/// Runs `$call` on whichever source `$backend` holds, bound to `$source`
+macro_rules! dispatch {
+    ($backend:expr, $source:ident => $call:expr) => {
+        match $backend {
+            Backend::Synology($source) => $call,
+            Backend::FileStation($source) => $call,
+            Backend::Immich($source) => $call,
+            Backend::ImageUrl($source) => $call,
+            Backend::WebDav($source) => $call,
+            Backend::S3($source) => $call,
+        }
+    };
+}
+
+impl PhotoSource for Backend {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        dispatch!(self, source => source.list().await)
+    }
+
+    fn select(&mut self, entries: &[PhotoEntry]) -> usize {
+        dispatch!(self, source => source.select(entries))
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        dispatch!(self, source => source.fetch(entry).await)
+    }
+
+    fn session(&self) -> Option<&str> {
+        dispatch!(self, source => source.session())
+    }
+
+    fn restore_session(&mut self, session: String) {
+        dispatch!(self, source => source.restore_session(session))
+    }
+
+    fn stable_ids(&self) -> bool {
+        dispatch!(self, source => source.stable_ids())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedListing {
+    session: Option<String>,
+    entries: Vec<PhotoEntry>,
+}
+
+fn store_listing<S: Storage>(
+    cache: Option<&mut AlbumCache<S>>,
+    source: &impl PhotoSource,
+    entries: &[PhotoEntry],
+) {
+    if let Some(cache) = cache {
+        cache.store(&CachedListing {
+            session: source.session().map(ToString::to_string),
+            entries: entries.to_vec(),
+        });
+    }
+}
+
+/// Picks a photo and decodes it, listing the source only when there's no fresh cached listing.
+/// Falls back to another photo if one fails to download or decode
+pub async fn get_photo<S: Storage>(
+    source: &mut impl PhotoSource,
+    deadline: Instant,
+    mut cache: Option<&mut AlbumCache<S>>,
+    cache_max_age_secs: u32,
+) -> Result<Photo, Error> {
+    let policy = RetryPolicy::DEFAULT;
+
+    let cached: Option<CachedListing> = cache
+        .as_deref_mut()
+        .and_then(|cache| cache.load(cache_max_age_secs));
+
+    // A cached listing gets one chance to be refreshed if it turns out stale
+    let mut listing_cached = cached.is_some();
+
+    let mut entries = match cached {
+        Some(cached) => {
+            if let Some(session) = cached.session {
+                source.restore_session(session);
+            }
+            cached.entries
+        }
+        None => {
+            let entries = retry(&policy, deadline, "list", async || source.list().await).await?;
+            store_listing(cache.as_deref_mut(), source, &entries);
+            entries
+        }
+    };
+
+    let mut session = source.session().map(ToString::to_string);
+
+    // Photos still worth trying, `entries` stays the whole listing for the cache
+    let mut candidates = entries.clone();
+
+    let mut last_error = Error::Empty;
+    let mut attempts = 0;
+    while attempts < MAX_PHOTO_ATTEMPTS && !candidates.is_empty() {
+        let index = source.select(&candidates);
+        let entry = candidates.swap_remove(index);
+
+        let bytes = retry(&policy, deadline, "fetch", async || {
+            source.fetch(&entry).await
+        })
+        .await;
+
+        if listing_cached && matches!(bytes, Err(Error::NotFound)) {
+            info!(
+                "[SRC] Cached photo {} is gone, listing again",
+                entry.id.as_str()
+            );
+            listing_cached = false;
+            if let Some(cache) = cache.as_deref_mut() {
+                cache.invalidate();
+            }
+            entries = retry(&policy, deadline, "list", async || source.list().await).await?;
+            store_listing(cache.as_deref_mut(), source, &entries);
+            candidates = entries.clone();
+            continue;
+        }
+
+        attempts += 1;
+
+        // Source had to log in again, keep the new session for next time
+        if source.session() != session.as_deref() {
+            session = source.session().map(ToString::to_string);
+            store_listing(cache.as_deref_mut(), source, &entries);
+        }
+
+        let result = bytes.and_then(|bytes| decode_jpeg(bytes).map_err(Error::Decode));
+        match result {
+            Ok(image) => return Ok(Photo { image, entry }),
+            Err(e) => {
+                warn!(
+                    "[SRC] Photo {} failed, trying another: {:?}",
+                    entry.id.as_str(),
+                    e
+                );
+                // Out of time, no point trying another photo
+                if matches!(e, Error::Http(http::Error::Timeout)) {
+                    return Err(e);
+                }
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
@@ -0,0 +1,63 @@
+// Small bit of state that survives deep sleep in RTC fast memory.
+// It's gone after a power loss or reflash which is fine for everything we keep here
+
+/// Marks the words as written by us and not left over garbage from power on
+const MAGIC: u32 = 0x5346_5231;
+
+const WORDS: usize = 8;
+
+#[esp_hal::ram(unstable(rtc_fast, persistent))]
+static mut RTC_WORDS: [u32; WORDS] = [0; WORDS];
+
+#[derive(Clone, Copy, Default)]
+pub struct RtcState {
+    /// Fingerprint of the credentials Synology rejected, 0 when nothing was rejected
+    pub rejected_credentials: u32,
+    /// Synology error code that came with the rejection
+    pub rejected_code: u16,
+    /// Low battery notification already went out for this discharge
+    pub low_battery_notified: bool,
+}
+
+impl RtcState {
+    pub fn credentials_rejected(&self, fingerprint: u32) -> bool {
+        self.rejected_credentials != 0 && self.rejected_credentials == fingerprint
+    }
+}
+
+pub fn load() -> RtcState {
+    let words = unsafe { (&raw const RTC_WORDS).read_volatile() };
+
+    if words[0] != MAGIC {
+        return RtcState::default();
+    }
+
+    RtcState {
+        rejected_credentials: words[1],
+        rejected_code: words[2] as u16,
+        low_battery_notified: words[3] != 0,
+    }
+}
+
+pub fn store(state: &RtcState) {
+    let mut words = [0u32; WORDS];
+    words[0] = MAGIC;
+    words[1] = state.rejected_credentials;
+    words[2] = state.rejected_code as u32;
+    words[3] = state.low_battery_notified as u32;
+
+    unsafe { (&raw mut RTC_WORDS).write_volatile(words) };
+}
+
+/// FNV-1a, just to notice when the config changes between flashes
+pub fn fingerprint(parts: &[&str]) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for part in parts {
+        for byte in part.bytes().chain(core::iter::once(0)) {
+            hash ^= byte as u32;
+            hash = hash.wrapping_mul(0x0100_0193);
+        }
+    }
+    // 0 means "nothing stored"
+    hash.max(1)
+}
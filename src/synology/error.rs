@@ -0,0 +1,161 @@
+// Synology answers most failures with a 200 and `{"success": false, "error": {"code": N}}`.
+// The 100 range is shared by every API, everything above that depends on which API you called.
+// See the "Common Error Codes" and per API tables in the DSM Login Web API / Synology Photos docs
+
+/// Which API a code came from, the same number means different things for each
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Api {
+    Auth,
+    FotoBrowse,
+    Thumbnail,
+    FileStation,
+    /// `SYNO.API.Info`, only the common codes
+    Info,
+    /// DSM 6 Photo Station, only the common codes
+    PhotoStation,
+    /// Photo Station's own `auth.php`, its codes aren't DSM's login codes
+    PhotoStationAuth,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum ErrorKind {
+    Unknown,
+    InvalidParameter,
+    NoSuchApi,
+    NoSuchMethod,
+    VersionNotSupported,
+    PermissionDenied,
+    SessionExpired,
+    /// NAS says it's busy or the network is flaky, worth another go
+    Busy,
+    IpMismatch,
+
+    BadCredentials,
+    AccountDisabled,
+    TwoFactorRequired,
+    TwoFactorFailed,
+    IpBlocked,
+    PasswordExpired,
+
+    NotFound,
+}
+
+type Table = &'static [(u16, ErrorKind, &'static str)];
+
+#[rustfmt::skip]
+const COMMON: Table = &[
+    (100, ErrorKind::Unknown, "UNKNOWN SYNOLOGY ERROR"),
+    (101, ErrorKind::InvalidParameter, "MISSING API, METHOD OR VERSION"),
+    (102, ErrorKind::NoSuchApi, "API DOES NOT EXIST, IS THE PACKAGE INSTALLED?"),
+    (103, ErrorKind::NoSuchMethod, "API METHOD DOES NOT EXIST"),
+    (104, ErrorKind::VersionNotSupported, "API VERSION NOT SUPPORTED, UPDATE DSM"),
+    (105, ErrorKind::PermissionDenied, "ACCOUNT HAS NO PERMISSION FOR THIS"),
+    (106, ErrorKind::SessionExpired, "SESSION TIMED OUT"),
+    (107, ErrorKind::SessionExpired, "SESSION INTERRUPTED BY ANOTHER LOGIN"),
+    (109, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (110, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (111, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (114, ErrorKind::InvalidParameter, "MISSING API PARAMETERS"),
+    (117, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (118, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (119, ErrorKind::SessionExpired, "INVALID SESSION"),
+    (120, ErrorKind::InvalidParameter, "INVALID API PARAMETER"),
+    (150, ErrorKind::IpMismatch, "REQUEST IP DOES NOT MATCH THE LOGIN IP"),
+];
+
+#[rustfmt::skip]
+const AUTH: Table = &[
+    (400, ErrorKind::BadCredentials, "NO SUCH ACCOUNT OR WRONG PASSWORD"),
+    (401, ErrorKind::AccountDisabled, "ACCOUNT IS DISABLED"),
+    (402, ErrorKind::PermissionDenied, "ACCOUNT IS NOT ALLOWED TO LOG IN"),
+    (403, ErrorKind::TwoFactorRequired, "ACCOUNT NEEDS 2FA, USE ONE WITHOUT IT"),
+    (404, ErrorKind::TwoFactorFailed, "2FA CODE WAS REJECTED"),
+    (406, ErrorKind::TwoFactorRequired, "DSM ENFORCES 2FA FOR THIS ACCOUNT"),
+    (407, ErrorKind::IpBlocked, "THIS IP IS BLOCKED BY DSM AUTO BLOCK"),
+    (408, ErrorKind::PasswordExpired, "PASSWORD EXPIRED AND CAN'T BE CHANGED"),
+    (409, ErrorKind::PasswordExpired, "PASSWORD EXPIRED"),
+    (410, ErrorKind::PasswordExpired, "PASSWORD MUST BE CHANGED"),
+];
+
+// Photo Station only tells a failed login apart, anything else comes back with a common code.
+// Unknown codes here are never taken as bad credentials
+#[rustfmt::skip]
+const PHOTO_STATION_AUTH: Table = &[
+    (400, ErrorKind::BadCredentials, "NO SUCH ACCOUNT OR WRONG PASSWORD"),
+];
+
+const FOTO: Table = &[(620, ErrorKind::NotFound, "PHOTO OR ALBUM NOT FOUND")];
+
+#[rustfmt::skip]
+const FILE_STATION: Table = &[
+    (400, ErrorKind::InvalidParameter, "INVALID FOLDER PATH"),
+    (402, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+    (407, ErrorKind::PermissionDenied, "ACCOUNT CAN'T READ THAT FOLDER"),
+    (408, ErrorKind::NotFound, "FOLDER OR FILE NOT FOUND"),
+    (418, ErrorKind::InvalidParameter, "ILLEGAL FOLDER PATH"),
+    (421, ErrorKind::Busy, "NAS IS BUSY OR NETWORK IS UNSTABLE"),
+];
+
+fn table(api: Api) -> Table {
+    match api {
+        Api::Auth => AUTH,
+        Api::FotoBrowse | Api::Thumbnail => FOTO,
+        Api::FileStation => FILE_STATION,
+        Api::PhotoStationAuth => PHOTO_STATION_AUTH,
+        Api::Info | Api::PhotoStation => &[],
+    }
+}
+
+fn lookup(api: Api, code: u16) -> Option<&'static (u16, ErrorKind, &'static str)> {
+    table(api)
+        .iter()
+        .chain(COMMON.iter())
+        .find(|(c, _, _)| *c == code)
+}
+
+#[derive(Clone, Copy, Debug, defmt::Format)]
+pub struct ApiError {
+    pub api: Api,
+    pub code: u16,
+    /// `None` for codes we don't know about
+    pub kind: Option<ErrorKind>,
+}
+
+impl ApiError {
+    pub fn decode(api: Api, code: u16) -> Self {
+        Self {
+            api,
+            code,
+            kind: lookup(api, code).map(|(_, kind, _)| *kind),
+        }
+    }
+
+    /// Short uppercase message that fits on the panel
+    pub fn message(&self) -> &'static str {
+        lookup(self.api, self.code).map_or("UNKNOWN SYNOLOGY ERROR", |(_, _, message)| message)
+    }
+
+    /// Logging in again won't help and will eventually get the IP blocked
+    pub fn is_auth_rejected(&self) -> bool {
+        match self.api {
+            Api::Auth => matches!(
+                self.kind,
+                Some(
+                    ErrorKind::BadCredentials
+                        | ErrorKind::AccountDisabled
+                        | ErrorKind::PermissionDenied
+                        | ErrorKind::TwoFactorRequired
+                        | ErrorKind::TwoFactorFailed
+                        | ErrorKind::IpBlocked
+                        | ErrorKind::PasswordExpired
+                )
+            ),
+            Api::PhotoStationAuth => self.kind == Some(ErrorKind::BadCredentials),
+            _ => false,
+        }
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        matches!(self.kind, Some(ErrorKind::Busy))
+    }
+}
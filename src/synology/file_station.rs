@@ -0,0 +1,267 @@
+use alloc::collections::VecDeque;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{info, warn};
+
+use super::{Api, Error, ErrorKind, image_response, login, parse_response};
+use crate::http::{self, Endpoint, HttpClient};
+use crate::source::{self, MAX_PHOTOS, PhotoEntry, PhotoSource, is_jpeg};
+
+extern crate alloc;
+
+// Plain shared folder through File Station, for photos Synology Photos doesn't index.
+// Same login as Synology Photos, the account needs read access to the folder
+
+/// How many folders deep a recursive listing goes
+const MAX_DEPTH: u8 = 4;
+/// Files asked for per request, a folder with more is listed a page at a time
+const PAGE_SIZE: usize = 500;
+
+pub struct FileStationSource {
+    client: HttpClient,
+    endpoint: Endpoint,
+    user: String,
+    pass: String,
+    /// e.g. `/photo/Frame`, shared folder first
+    folder: String,
+    recursive: bool,
+    sid: Option<String>,
+    /// `sid` came from the cache and may have expired since
+    sid_restored: bool,
+}
+
+impl FileStationSource {
+    pub fn new(
+        client: HttpClient,
+        endpoint: Endpoint,
+        user: &str,
+        pass: &str,
+        folder: &str,
+        recursive: bool,
+    ) -> Self {
+        Self {
+            client,
+            endpoint,
+            user: user.to_string(),
+            pass: pass.to_string(),
+            folder: folder.trim_end_matches('/').to_string(),
+            recursive,
+            sid: None,
+            sid_restored: false,
+        }
+    }
+
+    async fn login(&mut self) -> Result<String, Error> {
+        let sid = login(&mut self.client, &self.endpoint, &self.user, &self.pass).await?;
+        self.sid = Some(sid.clone());
+        self.sid_restored = false;
+        Ok(sid)
+    }
+
+    /// Thumbnail first since originals can be huge, the original if there is no thumbnail
+    async fn download(&mut self, sid: &str, path: &str) -> Result<Vec<u8>, Error> {
+        match get_thumbnail(&mut self.client, &self.endpoint, sid, path).await {
+            Err(Error::Api(e)) if e.kind != Some(ErrorKind::SessionExpired) => {
+                warn!("[SYN] No thumbnail for {}, downloading the original", path);
+                get_file(&mut self.client, &self.endpoint, sid, path).await
+            }
+            Err(Error::InvalidResponse) => {
+                get_file(&mut self.client, &self.endpoint, sid, path).await
+            }
+            result => result,
+        }
+    }
+}
+
+impl PhotoSource for FileStationSource {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, source::Error> {
+        let sid = self.login().await?;
+
+        let mut entries = Vec::new();
+        let mut folders = VecDeque::from([(self.folder.clone(), 0u8)]);
+
+        // Stops as soon as there are enough, no point listing the rest of a big tree
+        while let Some((folder, depth)) = folders.pop_front()
+            && entries.len() < MAX_PHOTOS
+        {
+            let mut offset = 0;
+            loop {
+                let page =
+                    list_folder(&mut self.client, &self.endpoint, &sid, &folder, offset).await?;
+                let done = page.files.is_empty() || offset + page.files.len() >= page.total;
+                offset += page.files.len();
+
+                for file in page.files {
+                    if file.is_dir {
+                        if self.recursive && depth < MAX_DEPTH {
+                            folders.push_back((file.path, depth + 1));
+                        }
+                    } else if is_jpeg(&file.name) && entries.len() < MAX_PHOTOS {
+                        entries.push(PhotoEntry {
+                            id: file.path,
+                            key: None,
+                            name: Some(file.name),
+                            taken_at: file.modified,
+                            focus: None,
+                        });
+                    }
+                }
+
+                if done || entries.len() >= MAX_PHOTOS {
+                    break;
+                }
+            }
+        }
+
+        info!("[SYN] Folder has {} photos", entries.len());
+
+        if entries.is_empty() {
+            return Err(source::Error::Empty);
+        }
+
+        Ok(entries)
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, source::Error> {
+        let sid = match &self.sid {
+            Some(sid) => sid.clone(),
+            None => self.login().await?,
+        };
+
+        match self.download(&sid, &entry.id).await {
+            Err(Error::Api(e))
+                if self.sid_restored && e.kind == Some(ErrorKind::SessionExpired) =>
+            {
+                info!("[SYN] Cached session expired, logging in again");
+                let sid = self.login().await?;
+                Ok(self.download(&sid, &entry.id).await?)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn session(&self) -> Option<&str> {
+        self.sid.as_deref()
+    }
+
+    fn restore_session(&mut self, session: String) {
+        self.sid = Some(session);
+        self.sid_restored = true;
+    }
+}
+
+struct FolderItem {
+    path: String,
+    name: String,
+    is_dir: bool,
+    /// Modified time, unix seconds
+    modified: Option<i64>,
+}
+
+/// One page of a folder listing
+struct FolderPage {
+    files: Vec<FolderItem>,
+    /// Everything in the folder, not just this page
+    total: usize,
+}
+
+async fn list_folder(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    folder: &str,
+    offset: usize,
+) -> Result<FolderPage, Error> {
+    let offset = offset.to_string();
+    let limit = PAGE_SIZE.to_string();
+    let url = url::Url::parse_with_params(
+        endpoint.url("/webapi/entry.cgi").as_str(),
+        &[
+            ("api", "SYNO.FileStation.List"),
+            ("version", "2"),
+            ("method", "list"),
+            ("folder_path", folder),
+            ("additional", "[\"time\"]"),
+            ("offset", offset.as_str()),
+            ("limit", limit.as_str()),
+            ("_sid", sid),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    let data = http::get(client, url.as_str(), &endpoint.headers()).await?;
+    let stuff = parse_response(Api::FileStation, &data)?;
+
+    let files = stuff["data"]["files"]
+        .as_array()
+        .ok_or(Error::InvalidResponse)?;
+
+    Ok(FolderPage {
+        files: files
+            .iter()
+            .filter_map(|file| {
+                Some(FolderItem {
+                    path: file["path"].as_str()?.to_string(),
+                    name: file["name"].as_str()?.to_string(),
+                    is_dir: file["isdir"].as_bool().unwrap_or(false),
+                    modified: file["additional"]["time"]["mtime"].as_i64(),
+                })
+            })
+            .collect(),
+        // Missing means the page is everything
+        total: stuff["data"]["total"].as_u64().unwrap_or(0) as usize,
+    })
+}
+
+async fn get_thumbnail(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    path: &str,
+) -> Result<Vec<u8>, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/webapi/entry.cgi").as_str(),
+        &[
+            ("api", "SYNO.FileStation.Thumb"),
+            ("version", "2"),
+            ("method", "get"),
+            ("path", path),
+            ("size", "large"),
+            ("_sid", sid),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    get_image(client, endpoint, url.as_str()).await
+}
+
+async fn get_file(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    path: &str,
+) -> Result<Vec<u8>, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/webapi/entry.cgi").as_str(),
+        &[
+            ("api", "SYNO.FileStation.Download"),
+            ("version", "2"),
+            ("method", "download"),
+            ("path", path),
+            ("mode", "download"),
+            ("_sid", sid),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    get_image(client, endpoint, url.as_str()).await
+}
+
+async fn get_image(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    url: &str,
+) -> Result<Vec<u8>, Error> {
+    let data = http::get(client, url, &endpoint.headers()).await?;
+    image_response(Api::FileStation, data)
+}
@@ -0,0 +1,458 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{error, info};
+use {esp_backtrace as _, esp_println as _};
+
+use crate::http::{self, Endpoint, HttpClient};
+use crate::images::Focus;
+use crate::source::{self, PhotoEntry, PhotoSource};
+
+extern crate alloc;
+
+mod error;
+mod file_station;
+mod photo_station;
+
+pub use error::{Api, ApiError, ErrorKind};
+pub use file_station::FileStationSource;
+
+/// Album items asked for per request, an album with more is listed a page at a time
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Clone)]
+pub struct AlbumItem {
+    pub id: i64,
+    pub cache_key: String,
+    pub filename: Option<String>,
+    /// Taken time, unix seconds
+    pub time: Option<i64>,
+    /// Box around the tagged faces
+    pub focus: Option<Focus>,
+}
+
+#[derive(Debug, defmt::Format)]
+pub enum Error {
+    Http(http::Error),
+    /// Synology answered with `success: false`
+    Api(ApiError),
+    /// Login failed in a way that logging in again won't fix. Retrying these
+    /// trips DSM's auto-block which locks out the whole IP
+    CredentialsRejected(ApiError),
+    /// Response didn't look like what we expected
+    InvalidResponse,
+    EmptyAlbum,
+}
+
+impl From<Error> for source::Error {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Http(e) => e.into(),
+            Error::Api(e) if e.kind == Some(ErrorKind::NotFound) => source::Error::NotFound,
+            Error::Api(e) => source::Error::Api {
+                code: e.code,
+                message: e.message(),
+                retryable: e.is_retryable(),
+            },
+            Error::CredentialsRejected(e) => source::Error::CredentialsRejected {
+                code: e.code,
+                message: e.message(),
+            },
+            Error::InvalidResponse => source::Error::InvalidResponse,
+            Error::EmptyAlbum => source::Error::Empty,
+        }
+    }
+}
+
+impl From<http::Error> for Error {
+    fn from(e: http::Error) -> Self {
+        Error::Http(e)
+    }
+}
+
+/// Which photo app the NAS runs
+#[derive(Clone, Copy, PartialEq, Eq, Debug, defmt::Format)]
+pub enum Flavor {
+    /// Ask `SYNO.API.Info` the first time we list
+    Auto,
+    /// Synology Photos, DSM 7
+    Photos,
+    /// Photo Station, DSM 6
+    PhotoStation,
+}
+
+/// Synology Photos shared album, or a Photo Station album on DSM 6.
+/// For Synology Photos the album passphrase comes from the share link,
+/// for Photo Station it's the `album_...` id from the album's URL
+pub struct SynologySource {
+    client: HttpClient,
+    endpoint: Endpoint,
+    flavor: Flavor,
+    user: String,
+    pass: String,
+    album_passphrase: String,
+    sid: Option<String>,
+    /// `sid` came from the cache and may have expired since
+    sid_restored: bool,
+}
+
+impl SynologySource {
+    pub fn new(
+        client: HttpClient,
+        endpoint: Endpoint,
+        flavor: Flavor,
+        user: &str,
+        pass: &str,
+        album_passphrase: &str,
+    ) -> Self {
+        Self {
+            client,
+            endpoint,
+            flavor,
+            user: user.to_string(),
+            pass: pass.to_string(),
+            album_passphrase: album_passphrase.to_string(),
+            sid: None,
+            sid_restored: false,
+        }
+    }
+
+    async fn login(&mut self, flavor: Flavor) -> Result<String, Error> {
+        let sid = match flavor {
+            Flavor::PhotoStation => {
+                photo_station::login(&mut self.client, &self.endpoint, &self.user, &self.pass)
+                    .await?
+            }
+            _ => login(&mut self.client, &self.endpoint, &self.user, &self.pass).await?,
+        };
+        self.sid = Some(sid.clone());
+        self.sid_restored = false;
+        Ok(sid)
+    }
+
+    async fn thumbnail(
+        &mut self,
+        flavor: Flavor,
+        sid: &str,
+        entry: &PhotoEntry,
+    ) -> Result<Vec<u8>, Error> {
+        match flavor {
+            Flavor::PhotoStation => {
+                photo_station::get_thumbnail(&mut self.client, &self.endpoint, sid, &entry.id).await
+            }
+            _ => {
+                get_thumbnail(
+                    &mut self.client,
+                    &self.endpoint,
+                    sid,
+                    &self.album_passphrase,
+                    &entry.id,
+                    entry.key.as_deref().unwrap_or_default(),
+                )
+                .await
+            }
+        }
+    }
+}
+
+impl PhotoSource for SynologySource {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, source::Error> {
+        if self.flavor == Flavor::Auto {
+            self.flavor = detect(&mut self.client, &self.endpoint).await?;
+        }
+
+        let sid = self.login(self.flavor).await?;
+
+        if self.flavor == Flavor::PhotoStation {
+            return Ok(photo_station::list_album(
+                &mut self.client,
+                &self.endpoint,
+                &sid,
+                &self.album_passphrase,
+            )
+            .await?);
+        }
+
+        let items = list_album(
+            &mut self.client,
+            &self.endpoint,
+            &sid,
+            &self.album_passphrase,
+        )
+        .await?;
+
+        Ok(items
+            .into_iter()
+            .map(|item| PhotoEntry {
+                id: item.id.to_string(),
+                key: Some(item.cache_key),
+                name: item.filename,
+                taken_at: item.time,
+                focus: item.focus,
+            })
+            .collect())
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, source::Error> {
+        // Listing came from the cache so we never asked, the ids tell them apart
+        let flavor = match self.flavor {
+            Flavor::Auto if entry.id.starts_with(photo_station::ID_PREFIX) => Flavor::PhotoStation,
+            Flavor::Auto => Flavor::Photos,
+            flavor => flavor,
+        };
+
+        let sid = match &self.sid {
+            Some(sid) => sid.clone(),
+            None => self.login(flavor).await?,
+        };
+
+        match self.thumbnail(flavor, &sid, entry).await {
+            Err(Error::Api(e))
+                if self.sid_restored && e.kind == Some(ErrorKind::SessionExpired) =>
+            {
+                info!("[SYN] Cached session expired, logging in again");
+                let sid = self.login(flavor).await?;
+                Ok(self.thumbnail(flavor, &sid, entry).await?)
+            }
+            result => Ok(result?),
+        }
+    }
+
+    fn session(&self) -> Option<&str> {
+        self.sid.as_deref()
+    }
+
+    fn restore_session(&mut self, session: String) {
+        self.sid = Some(session);
+        self.sid_restored = true;
+    }
+}
+
+/// Asks DSM which photo app is installed, Synology Photos wins if somehow both are
+pub async fn detect(client: &mut HttpClient, endpoint: &Endpoint) -> Result<Flavor, Error> {
+    let candidates = [
+        (Flavor::Photos, "/webapi/query.cgi", "SYNO.Foto.Browse.Item"),
+        (
+            Flavor::PhotoStation,
+            "/photo/webapi/query.php",
+            "SYNO.PhotoStation.Auth",
+        ),
+    ];
+
+    for (flavor, path, api) in candidates {
+        let url = url::Url::parse_with_params(
+            endpoint.url(path).as_str(),
+            &[
+                ("api", "SYNO.API.Info"),
+                ("version", "1"),
+                ("method", "query"),
+                ("query", api),
+            ],
+        )
+        .map_err(|_| Error::InvalidResponse)?;
+
+        // No Photo Station means no `/photo/webapi` at all, that's a 404 and not an error here
+        let data = match http::get(client, url.as_str(), &endpoint.headers()).await {
+            Err(http::Error::Status(404)) => continue,
+            result => result?,
+        };
+
+        if let Ok(stuff) = parse_response(Api::Info, &data)
+            && !stuff["data"][api].is_null()
+        {
+            info!("[SYN] Detected {:?}", flavor);
+            return Ok(flavor);
+        }
+    }
+
+    error!("[SYN] Neither Synology Photos nor Photo Station found");
+    Err(Error::Api(ApiError::decode(Api::Info, 102)))
+}
+
+pub async fn login(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    user: &str,
+    pass: &str,
+) -> Result<String, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/webapi/entry.cgi").as_str(),
+        &[
+            ("api", "SYNO.API.Auth"),
+            ("version", "6"),
+            ("method", "login"),
+            ("format", "sid"),
+            ("account", user),
+            ("passwd", pass),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    info!("[HTTP] Getting auth token");
+
+    let data = http::get(client, url.as_str(), &endpoint.headers()).await?;
+    let stuff = match parse_response(Api::Auth, &data) {
+        Err(Error::Api(e)) if e.is_auth_rejected() => {
+            error!("[SYN] Credentials rejected: {}", e.message());
+            return Err(Error::CredentialsRejected(e));
+        }
+        other => other?,
+    };
+
+    let sid = stuff["data"]["sid"]
+        .as_str()
+        .ok_or(Error::InvalidResponse)?
+        .to_string();
+    info!("[HTTP] Auth SID: {:?}", sid.as_str());
+
+    Ok(sid)
+}
+
+/// Union of the face boxes in `additional.person`. Only there when face recognition is on
+/// and the NAS hands out boxes, which are in pixels of `additional.resolution`
+fn face_focus(additional: &serde_json::Value) -> Option<Focus> {
+    let width = additional["resolution"]["width"].as_f64()? as f32;
+    let height = additional["resolution"]["height"].as_f64()? as f32;
+    if width <= 0.0 || height <= 0.0 {
+        return None;
+    }
+
+    additional["person"]
+        .as_array()?
+        .iter()
+        .filter_map(|person| {
+            let face = &person["bounding_box"];
+            Some(Focus {
+                x: face["x"].as_f64()? as f32 / width,
+                y: face["y"].as_f64()? as f32 / height,
+                width: face["width"].as_f64()? as f32 / width,
+                height: face["height"].as_f64()? as f32 / height,
+            })
+        })
+        .reduce(Focus::union)
+}
+
+pub async fn list_album(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    album_passphrase: &str,
+) -> Result<Vec<AlbumItem>, Error> {
+    let mut items = Vec::new();
+    let mut offset = 0;
+    let limit = PAGE_SIZE.to_string();
+
+    while items.len() < source::MAX_PHOTOS {
+        let page_offset = offset.to_string();
+        let url = url::Url::parse_with_params(
+            endpoint
+                .url("/webapi/entry.cgi/SYNO.Foto.Browse.Item")
+                .as_str(),
+            &[
+                ("api", "SYNO.Foto.Browse.Item"),
+                ("version", "4"),
+                ("method", "list"),
+                ("additional", "[\"thumbnail\",\"resolution\",\"person\"]"),
+                ("sort_by", "takentime"),
+                ("offset", page_offset.as_str()),
+                ("limit", limit.as_str()),
+                ("sort_direction", "asc"),
+                ("passphrase", album_passphrase),
+                ("_sid", sid),
+            ],
+        )
+        .map_err(|_| Error::InvalidResponse)?;
+
+        let data = http::get(client, url.as_str(), &endpoint.headers()).await?;
+        let stuff = parse_response(Api::FotoBrowse, &data)?;
+
+        let album_list = stuff["data"]["list"]
+            .as_array()
+            .ok_or(Error::InvalidResponse)?;
+
+        // Skip anything without a thumbnail instead of failing the whole album
+        items.extend(album_list.iter().filter_map(|photo_object| {
+            Some(AlbumItem {
+                id: photo_object["id"].as_i64()?,
+                cache_key: photo_object["additional"]["thumbnail"]["cache_key"]
+                    .as_str()?
+                    .to_string(),
+                filename: photo_object["filename"].as_str().map(ToString::to_string),
+                time: photo_object["time"].as_i64(),
+                focus: face_focus(&photo_object["additional"]),
+            })
+        }));
+
+        // No total in the reply, a short page is the last one
+        offset += album_list.len();
+        if album_list.len() < PAGE_SIZE {
+            break;
+        }
+    }
+    items.truncate(source::MAX_PHOTOS);
+
+    info!("[SYN] Album has {} photos", items.len());
+
+    if items.is_empty() {
+        return Err(Error::EmptyAlbum);
+    }
+
+    Ok(items)
+}
+
+pub async fn get_thumbnail(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    album_passphrase: &str,
+    id: &str,
+    cache_key: &str,
+) -> Result<Vec<u8>, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/synofoto/api/v2/t/Thumbnail/get").as_str(),
+        &[
+            ("api", "SYNO.Foto.Thumbnail"),
+            ("version", "1"),
+            ("method", "get"),
+            ("mode", "download"),
+            ("id", id),
+            ("type", "unit"),
+            ("size", "m"),
+            ("passphrase", album_passphrase),
+            ("cache_key", cache_key),
+            ("_sid", sid),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    let mut headers = endpoint.headers();
+    headers.push(("User-Agent", "ESP32S3"));
+    let data = http::get(client, url.as_str(), &headers).await?;
+    image_response(Api::Thumbnail, data)
+}
+
+/// Downloads answer errors as JSON with a 200 instead of an image
+fn image_response(api: Api, data: Vec<u8>) -> Result<Vec<u8>, Error> {
+    if data.first() == Some(&b'{') {
+        parse_response(api, &data)?;
+        return Err(Error::InvalidResponse);
+    }
+
+    Ok(data)
+}
+
+/// Parses the JSON envelope and turns `success: false` into an error
+fn parse_response(api: Api, data: &[u8]) -> Result<serde_json::Value, Error> {
+    let stuff: serde_json::Value = serde_json::from_slice(data).map_err(|_| {
+        error!("[SYN] Not JSON {:?}", core::str::from_utf8(data).ok());
+        Error::InvalidResponse
+    })?;
+
+    if stuff["success"].as_bool() == Some(false) {
+        let code = stuff["error"]["code"].as_u64().unwrap_or(100) as u16;
+        let e = ApiError::decode(api, code);
+        error!("[SYN] {:?} error {}: {}", api, code, e.message());
+        return Err(Error::Api(e));
+    }
+
+    Ok(stuff)
+}
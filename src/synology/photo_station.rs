@@ -0,0 +1,133 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use defmt::{error, info};
+
+use super::{Api, Error, image_response, parse_response};
+use crate::http::{self, Endpoint, HttpClient};
+use crate::source::PhotoEntry;
+
+extern crate alloc;
+
+// Photo Station on DSM 6. Lives under `/photo/webapi/*.php` instead of DSM's `entry.cgi`
+// and the session goes in a `PHPSESSID` cookie instead of `_sid`
+
+/// Photo Station ids look like `photo_<album>_<file>`, Synology Photos ids are numbers
+pub const ID_PREFIX: &str = "photo_";
+
+pub async fn login(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    user: &str,
+    pass: &str,
+) -> Result<String, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/photo/webapi/auth.php").as_str(),
+        &[
+            ("api", "SYNO.PhotoStation.Auth"),
+            ("version", "1"),
+            ("method", "login"),
+            ("username", user),
+            ("password", pass),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    info!("[HTTP] Getting Photo Station session");
+
+    let data = http::get(client, url.as_str(), &endpoint.headers()).await?;
+    let stuff = match parse_response(Api::PhotoStationAuth, &data) {
+        Err(Error::Api(e)) if e.is_auth_rejected() => {
+            error!("[SYN] Credentials rejected: {}", e.message());
+            return Err(Error::CredentialsRejected(e));
+        }
+        other => other?,
+    };
+
+    let sid = stuff["data"]["sid"]
+        .as_str()
+        .ok_or(Error::InvalidResponse)?
+        .to_string();
+
+    Ok(sid)
+}
+
+/// Photos directly in the album, `album_id` is the `album_...` part of the album's URL
+pub async fn list_album(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    album_id: &str,
+) -> Result<Vec<PhotoEntry>, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/photo/webapi/album.php").as_str(),
+        &[
+            ("api", "SYNO.PhotoStation.Album"),
+            ("version", "1"),
+            ("method", "list"),
+            ("id", album_id),
+            ("type", "photo"),
+            ("offset", "0"),
+            ("limit", "500"),
+            ("recursive", "false"),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    let cookie = format!("PHPSESSID={}", sid);
+    let mut headers = endpoint.headers();
+    headers.push(("Cookie", &cookie));
+    let data = http::get(client, url.as_str(), &headers).await?;
+    let stuff = parse_response(Api::PhotoStation, &data)?;
+
+    let items = stuff["data"]["items"]
+        .as_array()
+        .ok_or(Error::InvalidResponse)?;
+
+    let entries: Vec<PhotoEntry> = items
+        .iter()
+        .filter(|item| item["type"].as_str() == Some("photo"))
+        .filter_map(|item| {
+            Some(PhotoEntry {
+                id: item["id"].as_str()?.to_string(),
+                key: None,
+                name: item["info"]["name"].as_str().map(ToString::to_string),
+                taken_at: None,
+                focus: None,
+            })
+        })
+        .collect();
+
+    info!("[SYN] Photo Station album has {} photos", entries.len());
+
+    if entries.is_empty() {
+        return Err(Error::EmptyAlbum);
+    }
+
+    Ok(entries)
+}
+
+pub async fn get_thumbnail(
+    client: &mut HttpClient,
+    endpoint: &Endpoint,
+    sid: &str,
+    id: &str,
+) -> Result<Vec<u8>, Error> {
+    let url = url::Url::parse_with_params(
+        endpoint.url("/photo/webapi/thumb.php").as_str(),
+        &[
+            ("api", "SYNO.PhotoStation.Thumb"),
+            ("version", "1"),
+            ("method", "get"),
+            ("size", "large"),
+            ("id", id),
+        ],
+    )
+    .map_err(|_| Error::InvalidResponse)?;
+
+    let cookie = format!("PHPSESSID={}", sid);
+    let mut headers = endpoint.headers();
+    headers.push(("Cookie", &cookie));
+    let data = http::get(client, url.as_str(), &headers).await?;
+    image_response(Api::PhotoStation, data)
+}
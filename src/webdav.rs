@@ -0,0 +1,68 @@
+use alloc::vec::Vec;
+use defmt::{error, info};
+use frame_core::webdav::{file_url, parse_listing};
+
+use crate::http::{self, Endpoint, HttpClient};
+use crate::source::{Error, PhotoEntry, PhotoSource};
+
+extern crate alloc;
+
+// WebDAV folder, e.g. Nextcloud (`/remote.php/dav/files/<user>/Frame`) or Synology WebDAV Server.
+// Lists with PROPFIND depth 1 so only the folder itself, no subfolders
+
+/// On the panel when the login gets a 401
+const REJECTED: &str = "WEBDAV SERVER REJECTED THE LOGIN";
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?><d:propfind xmlns:d="DAV:"><d:prop><d:getcontenttype/><d:resourcetype/></d:prop></d:propfind>"#;
+
+pub struct WebDavSource {
+    client: HttpClient,
+    /// Base is the folder URL
+    endpoint: Endpoint,
+}
+
+impl WebDavSource {
+    pub fn new(client: HttpClient, endpoint: Endpoint) -> Self {
+        Self { client, endpoint }
+    }
+}
+
+impl PhotoSource for WebDavSource {
+    async fn list(&mut self) -> Result<Vec<PhotoEntry>, Error> {
+        // Some servers redirect a folder without the trailing `/`
+        let url = self.endpoint.url("/");
+
+        let data = http::propfind(
+            &mut self.client,
+            &url,
+            &self.endpoint.headers(),
+            1,
+            PROPFIND_BODY,
+        )
+        .await
+        .map_err(|e| Error::from_auth(e, REJECTED))?;
+
+        let data = core::str::from_utf8(&data).map_err(|_| {
+            error!("[DAV] Listing is not text");
+            Error::InvalidResponse
+        })?;
+
+        let entries = parse_listing(data);
+
+        info!("[DAV] {} photos", entries.len());
+
+        if entries.is_empty() {
+            return Err(Error::Empty);
+        }
+
+        Ok(entries)
+    }
+
+    async fn fetch(&mut self, entry: &PhotoEntry) -> Result<Vec<u8>, Error> {
+        let url = file_url(self.endpoint.base(), &entry.id)
+            .ok_or(Error::Http(http::Error::InvalidUrl))?;
+
+        http::get(&mut self.client, &url, &self.endpoint.headers())
+            .await
+            .map_err(|e| Error::from_auth(e, REJECTED))
+    }
+}